@@ -1,9 +1,10 @@
 use regex::Regex;
 use thiserror::Error;
 
-use furrow::{entries, Id};
+use furrow::{authority, entries, status};
 
 use super::Ref;
+use crate::hooks::pre_receive::secrets::Findings;
 
 /// An [`enum@Error`] that can occur while executing a hook.
 #[derive(Debug, Error)]
@@ -16,43 +17,79 @@ pub enum Error {
     #[error("Unable to process ref update: {0}")]
     RefUpdateParse(parse_display::ParseError),
 
+    #[error("Illegal push option: {0}.")]
+    IllegalPushOption(String),
+
     #[error("Ref `{0}` may not be deleted.")]
     DeleteRef(Ref),
 
+    #[error("Ref `{0}` is frozen ({1}).")]
+    Frozen(Ref, entries::Freeze),
+
     #[error("Non fast-forward updates are disabled on `{0}`.")]
     NonFastForward(Ref),
 
     #[error("The ref name `{0}` does not match {1}.")]
     IllegalRefName(String, Regex),
 
-    #[error("The repository `{0}` is not empty, and thus cannot be removed.")]
-    NonEmptyRepository(Id),
+    #[error("Ref `{0}` is outside of the namespaces allowed for this repository.")]
+    IllegalRef(Ref),
+
+    #[error("Ref `{0}` may only be updated by the keychain, push to `refs/for/<branch>` to submit a proposal instead.")]
+    ProposalOnly(Ref),
+
+    #[error("The target branch `{0}` of the proposal does not exist.")]
+    MissingTarget(Ref),
+
+    #[error("Ref `{0}` requires a successful `{1}` status on {2}.")]
+    MissingStatus(Ref, String, git2::Oid),
+
+    #[error("The path `{0}` modified in commit {1} is protected by `{2}`, and the key is not among it's owners.")]
+    ProtectedPath(String, git2::Oid, entries::Pattern),
+
+    #[error("{0}")]
+    SecretLeak(Findings),
+
+    #[error("Unable to parse `{0}`, the pattern `{1}` is invalid: {2}")]
+    IllegalPattern(&'static str, String, regex::Error),
 
     #[error("Unable to parse {0}")]
     EntryParse(#[from] entries::Error),
 
+    #[error(transparent)]
+    Authority(#[from] authority::Error),
+
+    #[error("Unable to read the commit statuses: {0}")]
+    Status(#[from] status::Error),
+
     #[error(transparent)]
     Git(#[from] git2::Error),
 
     #[error(transparent)]
     IO(#[from] std::io::Error),
+
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
 }
 
 impl Error {
     /// Acknowledge the error by outputing to `stdout`
     /// and exiting with the correct exit-code.
     pub fn acknowledge(self) -> ! {
-        match self {
-            Self::Hint(err) => {
-                println!("hint: {err}");
+        self.emit();
 
-                std::process::exit(0);
-            }
-            _ => {
-                println!("error: {self}");
+        match self {
+            Self::Hint(_) => std::process::exit(0),
+            _ => std::process::exit(1),
+        }
+    }
 
-                std::process::exit(1);
-            }
+    /// Output the error to `stdout` for it to be relayed to the client,
+    /// without exiting.
+    pub fn emit(&self) {
+        match self {
+            Self::Hint(err) => println!("hint: {err}"),
+            _ => println!("error: {self}"),
         }
     }
 
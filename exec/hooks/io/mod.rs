@@ -6,5 +6,8 @@ pub use error::Error;
 mod r#ref;
 pub use r#ref::{Ref, RefUpdate};
 
+mod options;
+pub use options::PushOptions;
+
 pub(super) mod params;
-pub use params::Params;
+pub use params::{Params, PUBLIC_KEY_ENV, REPOSITORY_ID_ENV, STORAGE_PATH_ENV};
@@ -0,0 +1,100 @@
+/// The name of the environment variable holding the count of push options, set by `git-receive-pack`.
+pub const PUSH_OPTION_COUNT_ENV: &str = "GIT_PUSH_OPTION_COUNT";
+
+/// The prefix of the environment variables holding each push option, set by `git-receive-pack`.
+pub const PUSH_OPTION_ENV_PREFIX: &str = "GIT_PUSH_OPTION_";
+
+/// The push options sent by the client with `git push -o <option>`,
+/// as passed by `git-receive-pack` to the hooks.
+///
+/// The options recognized by the server are the following:
+///
+/// - `ci.skip`: Do not trigger CI jobs for this push.
+/// - `notify=<bool>`: Whether to send notifications for this push, defaults to `true`.
+/// - `topic=<name>`: The topic of the changes in this push,
+///   successive pushes to `refs/for/<branch>` with the same topic updating the same proposal.
+///
+/// Any other option is ignored by the server.
+#[derive(Debug, Default, Clone)]
+pub struct PushOptions {
+    raw: Vec<String>,
+}
+
+impl PushOptions {
+    /// Collect the push options from the environment.
+    pub fn from_env() -> Self {
+        let count = std::env::var(PUSH_OPTION_COUNT_ENV)
+            .ok()
+            .and_then(|count| count.parse::<usize>().ok())
+            .unwrap_or_default();
+
+        Self {
+            raw: (0..count)
+                .filter_map(|idx| std::env::var(format!("{PUSH_OPTION_ENV_PREFIX}{idx}")).ok())
+                .collect(),
+        }
+    }
+
+    /// Wrap the `raw` push options, as received from `git-receive-pack` over the protocol.
+    pub fn new(raw: Vec<String>) -> Self {
+        Self { raw }
+    }
+
+    /// Iterate over the raw push options, as sent by the client.
+    pub fn raw(&self) -> impl Iterator<Item = &str> {
+        self.raw.iter().map(String::as_str)
+    }
+
+    /// Get the value of the last push option named `key`, if any,
+    /// `Some(None)` denoting an option without a value.
+    fn get(&self, key: &str) -> Option<Option<&str>> {
+        self.raw
+            .iter()
+            .rev()
+            .find_map(|option| match option.split_once('=') {
+                Some((k, value)) if k == key => Some(Some(value)),
+                None if option == key => Some(None),
+                _ => None,
+            })
+    }
+
+    /// Get the boolean value of the push option named `key`, if any.
+    fn get_bool(&self, key: &str) -> Result<Option<bool>, String> {
+        self.get(key)
+            .map(|value| match value {
+                None | Some("true" | "yes" | "1") => Ok(true),
+                Some("false" | "no" | "0") => Ok(false),
+                Some(value) => Err(format!("`{key}` expects a boolean, got `{value}`")),
+            })
+            .transpose()
+    }
+
+    /// Whether CI jobs should be skipped for this push, from the `ci.skip` option.
+    pub fn ci_skip(&self) -> bool {
+        self.get_bool("ci.skip").ok().flatten().unwrap_or(false)
+    }
+
+    /// Whether notifications should be sent for this push, from the `notify` option.
+    pub fn notify(&self) -> bool {
+        self.get_bool("notify").ok().flatten().unwrap_or(true)
+    }
+
+    /// The topic of the changes in this push, from the `topic` option.
+    pub fn topic(&self) -> Option<&str> {
+        self.get("topic")
+            .flatten()
+            .filter(|topic| !topic.is_empty())
+    }
+
+    /// Validate the values of the push options recognized by the server.
+    pub fn validate(&self) -> Result<(), String> {
+        self.get_bool("ci.skip")?;
+        self.get_bool("notify")?;
+
+        if let Some(None | Some("")) = self.get("topic") {
+            return Err("`topic` expects a non-empty name".into());
+        }
+
+        Ok(())
+    }
+}
@@ -1,15 +1,21 @@
 use std::path::PathBuf;
 
 use clap::Parser;
+use ssh_key::PublicKey;
 
 use furrow::Id;
 
+use super::PushOptions;
+
 /// The name of the environment variable used to pass the repository id to the hooks.
 pub const REPOSITORY_ID_ENV: &str = "REPOSITORY_ID";
 
 /// The name of the environment variable used to pass the global repositories storage path.
 pub const STORAGE_PATH_ENV: &str = "STORAGE_PATH";
 
+/// The name of the environment variable used to pass the public key of the pusher, in OpenSSH format.
+pub const PUBLIC_KEY_ENV: &str = "PUBLIC_KEY";
+
 /// A structure representing the `env` parameters required by the hooks.
 #[derive(Debug, Parser)]
 pub struct Params {
@@ -18,4 +24,10 @@ pub struct Params {
 
     #[arg(long, env = REPOSITORY_ID_ENV)]
     pub id: Id,
+
+    #[arg(long, env = PUBLIC_KEY_ENV)]
+    pub key: PublicKey,
+
+    #[arg(skip = PushOptions::from_env())]
+    pub options: PushOptions,
 }
@@ -3,18 +3,47 @@ use std::str::FromStr;
 use futures::{io::BufReader, AsyncBufReadExt, AsyncRead, Stream, TryStreamExt};
 use parse_display::{Display, FromStr};
 
-use furrow::Repository;
+use furrow::{proposal, Repository};
 
 use super::Error;
 
-/// An enum differentiating references of type [`Ref::Branch`] and of type [`Ref::Tag`].
-#[derive(Debug, FromStr, Display)]
+/// An enum differentiating references of type [`Ref::Branch`], of type [`Ref::Tag`]
+/// and references in any other namespace, such as `refs/notes/*`.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Ref {
-    #[display("refs/heads/{0}")]
     Branch(String),
-
-    #[display("refs/tags/{0}")]
     Tag(String),
+
+    /// Any other reference, by it's full name.
+    Other(String),
+}
+
+impl FromStr for Ref {
+    type Err = parse_display::ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(name) = s.strip_prefix("refs/heads/") {
+            Ok(Self::Branch(name.into()))
+        } else if let Some(name) = s.strip_prefix("refs/tags/") {
+            Ok(Self::Tag(name.into()))
+        } else if s.starts_with("refs/") {
+            Ok(Self::Other(s.into()))
+        } else {
+            Err(parse_display::ParseError::with_message(
+                "The reference is not under `refs/`",
+            ))
+        }
+    }
+}
+
+impl std::fmt::Display for Ref {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Branch(name) => write!(f, "refs/heads/{name}"),
+            Self::Tag(name) => write!(f, "refs/tags/{name}"),
+            Self::Other(name) => f.write_str(name),
+        }
+    }
 }
 
 /// A structure representing a ref update parsed from stdin.
@@ -57,4 +86,57 @@ impl RefUpdate {
     pub fn is_delete(&self) -> bool {
         !self.oldrev.is_zero() && self.newrev.is_zero()
     }
+
+    /// List the commits introduced in the repository by this update,
+    /// that is the commits not reachable from any of the other references.
+    ///
+    /// The references updated by the `push` are considered at their previous value,
+    /// so this works both before and after the references have been updated.
+    ///
+    /// The proposals are not considered, since their commits were only checked
+    /// as proposals and must still be checked once pushed to other references.
+    pub fn commits(
+        &self,
+        repository: &Repository,
+        push: &[RefUpdate],
+    ) -> Result<Vec<git2::Oid>, Error> {
+        if self.newrev.is_zero() {
+            return Ok(Vec::new());
+        }
+
+        let mut revwalk = repository.revwalk()?;
+        revwalk.push(self.newrev)?;
+        if !self.oldrev.is_zero() {
+            revwalk.hide(self.oldrev)?;
+        }
+
+        let pushed: Vec<_> = push
+            .iter()
+            .map(|update| update.refname.to_string())
+            .chain([self.refname.to_string()])
+            .collect();
+        for update in push {
+            if !update.oldrev.is_zero() {
+                revwalk.hide(update.oldrev)?;
+            }
+        }
+
+        for reference in repository.references()? {
+            let reference = reference?;
+
+            let Some(name) = reference.name() else {
+                continue;
+            };
+
+            if !name.starts_with(proposal::PROPOSALS_PREFIX)
+                && !pushed.iter().any(|pushed| pushed == name)
+            {
+                if let Ok(commit) = reference.peel_to_commit() {
+                    revwalk.hide(commit.id())?;
+                }
+            }
+        }
+
+        revwalk.collect::<Result<_, _>>().map_err(Into::into)
+    }
 }
@@ -4,15 +4,17 @@ use std::{collections::HashMap, path::Path};
 
 use clap::Parser;
 use color_eyre::eyre;
+use ssh_key::PublicKey;
 use strum::{EnumVariantNames, VariantNames};
 
-use furrow::Id;
+use furrow::{Id, Repository};
 
 pub mod io;
 use io::{Error, Params, Ref, RefUpdate};
 
-mod post_receive;
-mod pre_receive;
+pub mod post_receive;
+pub mod pre_receive;
+mod proc_receive;
 mod update;
 
 /// The collection of git hooks defined for this remote.
@@ -24,6 +26,8 @@ pub enum Hooks {
     PreReceive(pre_receive::PreReceive),
     /// Execute as a git `update` hook.
     Update(update::Update),
+    /// Execute as a git `proc-receive` hook.
+    ProcReceive(proc_receive::ProcReceive),
     /// Execute as a git `post-receive` hook.
     PostReceive(post_receive::PostReceive),
 }
@@ -34,6 +38,7 @@ impl Hooks {
         let result = match self {
             Hooks::PreReceive(hook) => hook.run().await,
             Hooks::Update(hook) => hook.run().await,
+            Hooks::ProcReceive(hook) => hook.run().await,
             Hooks::PostReceive(hook) => hook.run().await,
         };
 
@@ -71,12 +76,36 @@ impl Hooks {
         Ok(())
     }
 
-    /// Setup environment variables to successfully use [`Hooks`].
-    pub fn env(envs: &mut HashMap<String, String>, storage: &Path, id: &Id) {
+    /// Setup environment variables to successfully use [`Hooks`] and the LFS transfers,
+    /// which are also served to anonymous clients, without a `key`.
+    pub fn env(
+        envs: &mut HashMap<String, String>,
+        storage: &Path,
+        id: &Id,
+        key: Option<&PublicKey>,
+    ) -> Result<(), eyre::Error> {
         envs.insert(
             io::params::STORAGE_PATH_ENV.into(),
             storage.to_string_lossy().into(),
         );
         envs.insert(io::params::REPOSITORY_ID_ENV.into(), id.to_string());
+        if let Some(key) = key {
+            envs.insert(io::params::PUBLIC_KEY_ENV.into(), key.to_openssh()?);
+        }
+
+        Ok(())
     }
 }
+
+/// Compute the changes introduced by the `commit`, relative to it's first parent.
+fn changes<'r>(repository: &'r Repository, commit: &git2::Commit) -> Result<git2::Diff<'r>, Error> {
+    let parent = commit
+        .parents()
+        .next()
+        .map(|parent| parent.tree())
+        .transpose()?;
+
+    repository
+        .diff_tree_to_tree(parent.as_ref(), Some(&commit.tree()?), None)
+        .map_err(Into::into)
+}
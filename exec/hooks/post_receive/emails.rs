@@ -0,0 +1,288 @@
+use chrono::{DateTime, FixedOffset, Utc};
+use serde::{Deserialize, Serialize};
+
+use furrow::{
+    entries::{Entry, Global, Keychain, Mail, Notifications, Recipient},
+    queue::Queue,
+    Id, Repository,
+};
+
+use super::{Error, Params, Ref, RefUpdate};
+use crate::hooks::changes;
+
+/// The name of the [`Queue`] of pending [`Email`]s.
+pub const QUEUE: &str = "emails";
+
+/// The maximum count of commits sent individually per reference,
+/// the remaining ones being only listed in the summary.
+const MAX_COMMITS: usize = 20;
+
+/// A pending email, ready to be sent.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Email {
+    /// The envelope sender address.
+    pub from: String,
+
+    /// The envelope recipients addresses.
+    pub recipients: Vec<String>,
+
+    /// The full message, headers included, with `\n` line endings.
+    pub message: String,
+}
+
+/// Enqueue the notifications of the `updates` to the recipients of the `namespace` and the `spec`,
+/// a summary per reference followed by one email per new commit.
+pub fn enqueue(
+    params: &Params,
+    repository: &Repository,
+    updates: &[RefUpdate],
+    namespace: &Notifications,
+    spec: &Notifications,
+) -> Result<(), Error> {
+    if !spec.enabled.or(namespace.enabled).unwrap_or(true) {
+        return Ok(());
+    }
+
+    let recipients: Vec<_> = namespace
+        .recipients
+        .iter()
+        .chain(&spec.recipients)
+        .collect();
+    if recipients.is_empty() {
+        return Ok(());
+    }
+
+    let Some(mail) =
+        Global::load(&Repository::open(&params.storage, &Id::global_authority())?)?.mail
+    else {
+        return Ok(());
+    };
+    let keychain = Keychain::load(&Repository::open(
+        &params.storage,
+        &params.id.to_authority(),
+    )?)?;
+
+    let mut addresses: Vec<String> = recipients
+        .into_iter()
+        .flat_map(|recipient| match recipient {
+            Recipient::Owner(owner) => keychain.emails(owner).into_iter().map(Into::into).collect(),
+            Recipient::Address(address) => vec![address.to_string()],
+        })
+        .collect();
+    addresses.sort();
+    addresses.dedup();
+
+    if addresses.is_empty() {
+        return Ok(());
+    }
+
+    let fingerprint = params.key.fingerprint(Default::default());
+    let pusher = match keychain.email(&params.key) {
+        Some(email) => format!("{email} ({fingerprint})"),
+        None => fingerprint.to_string(),
+    };
+
+    let queue = Queue::<Email>::new(&params.storage, QUEUE);
+    let composer = Composer {
+        mail: &mail,
+        id: &params.id,
+        recipients: &addresses,
+        reply_to: keychain.email(&params.key),
+    };
+
+    for update in updates {
+        let (kind, name) = match &update.refname {
+            Ref::Branch(name) => ("branch", name.as_str()),
+            Ref::Tag(name) => ("tag", name.as_str()),
+            Ref::Other(name) => ("reference", name.as_str()),
+        };
+        let action = match (update.oldrev.is_zero(), update.newrev.is_zero()) {
+            (true, _) => "created",
+            (_, true) => "deleted",
+            _ if !update.is_ff(repository)? => "force-updated",
+            _ => "updated",
+        };
+
+        let mut commits = update.commits(repository, updates)?;
+        commits.reverse();
+
+        let mut body = format!(
+            "The {kind} `{name}` of the repository `{}` has been {action} by {pusher}.\n\n",
+            params.id
+        );
+        if !update.oldrev.is_zero() {
+            body.push_str(&format!("    from  {}\n", update.oldrev));
+        }
+        if !update.newrev.is_zero() {
+            body.push_str(&format!("      to  {}\n", update.newrev));
+        }
+        if !commits.is_empty() {
+            body.push_str(&format!(
+                "\nThis update added {} new commit(s):\n\n",
+                commits.len()
+            ));
+
+            for oid in &commits {
+                let commit = repository.find_commit(*oid)?;
+
+                body.push_str(&format!(
+                    "    {:.7} {}\n",
+                    oid.to_string(),
+                    commit.summary().unwrap_or_default()
+                ));
+            }
+
+            if commits.len() > MAX_COMMITS {
+                body.push_str(&format!(
+                    "\nOnly the first {MAX_COMMITS} commits are detailed in separate emails.\n"
+                ));
+            }
+        }
+
+        let (parent, summary) = composer.compose(
+            &format!("{kind} {name} {action}"),
+            &update.refname.to_string(),
+            None,
+            &body,
+        );
+        queue.push(summary)?;
+
+        let total = commits.len();
+        for (idx, oid) in commits.into_iter().take(MAX_COMMITS).enumerate() {
+            let commit = repository.find_commit(oid)?;
+            let author = commit.author();
+            let date = DateTime::from_timestamp(author.when().seconds(), 0)
+                .unwrap_or_default()
+                .with_timezone(
+                    &FixedOffset::east_opt(author.when().offset_minutes() * 60)
+                        .unwrap_or(FixedOffset::east_opt(0).expect("UTC is a valid offset")),
+                );
+
+            let mut body = format!(
+                "{pusher} pushed a commit to the {kind} `{name}` of the repository `{}`.\n\n",
+                params.id
+            );
+            body.push_str(&format!("commit {oid}\n"));
+            body.push_str(&format!("Author: {author}\n"));
+            body.push_str(&format!("Date:   {}\n\n", date.to_rfc2822()));
+            for line in commit.message().unwrap_or_default().trim_end().lines() {
+                body.push_str(&format!("    {line}\n"));
+            }
+            body.push_str("\n---\n");
+            body.push_str(
+                changes(repository, &commit)?
+                    .stats()?
+                    .to_buf(git2::DiffStatsFormat::FULL, 72)?
+                    .as_str()
+                    .unwrap_or_default(),
+            );
+
+            let (_, email) = composer.compose(
+                &format!(
+                    "{:0width$}/{total:0width$}: {}",
+                    idx + 1,
+                    commit.summary().unwrap_or_default(),
+                    width = total.to_string().len().max(2)
+                ),
+                &update.refname.to_string(),
+                Some(&parent),
+                &body,
+            );
+            queue.push(email)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// The shared parameters of the emails generated for a push.
+struct Composer<'c> {
+    mail: &'c Mail,
+    id: &'c Id,
+    recipients: &'c [String],
+    reply_to: Option<&'c str>,
+}
+
+impl Composer<'_> {
+    /// Compose an [`Email`] from the `subject` and `body`, in reply to the `parent` message if any,
+    /// returning it along with it's `Message-ID`.
+    fn compose(
+        &self,
+        subject: &str,
+        refname: &str,
+        parent: Option<&str>,
+        body: &str,
+    ) -> (String, Email) {
+        let domain = self
+            .mail
+            .from
+            .as_str()
+            .rsplit_once('@')
+            .map_or("localhost", |(_, domain)| domain);
+        let id = format!(
+            "<{}.{:08x}@{domain}>",
+            Utc::now().timestamp_nanos_opt().unwrap_or_default(),
+            rand::random::<u32>()
+        );
+
+        let mut headers = vec![
+            ("From", format!("furrow <{}>", self.mail.from)),
+            ("To", self.recipients.join(", ")),
+            ("Subject", encode(&format!("[{}] {subject}", self.id))),
+            ("Date", Utc::now().to_rfc2822()),
+            ("Message-ID", id.clone()),
+        ];
+        if let Some(reply_to) = self.reply_to {
+            headers.push(("Reply-To", reply_to.into()));
+        }
+        if let Some(parent) = parent {
+            headers.push(("In-Reply-To", parent.into()));
+            headers.push(("References", parent.into()));
+        }
+        headers.extend([
+            ("MIME-Version", "1.0".into()),
+            ("Content-Type", "text/plain; charset=utf-8".into()),
+            ("Content-Transfer-Encoding", "8bit".into()),
+            ("Auto-Submitted", "auto-generated".into()),
+            ("X-Furrow-Repository", self.id.to_string()),
+            ("X-Furrow-Ref", refname.into()),
+        ]);
+
+        let mut message = String::new();
+        for (name, value) in headers {
+            message.push_str(&format!("{name}: {value}\n"));
+        }
+        message.push('\n');
+        message.push_str(body);
+
+        (
+            id,
+            Email {
+                from: self.mail.from.to_string(),
+                recipients: self.recipients.to_vec(),
+                message,
+            },
+        )
+    }
+}
+
+/// Encode a header `value` as an RFC 2047 `Q` encoded-word if it isn't plain ASCII.
+fn encode(value: &str) -> String {
+    if value.is_ascii() && !value.contains(['\r', '\n']) {
+        return value.into();
+    }
+
+    let mut encoded = String::from("=?utf-8?q?");
+    for byte in value.bytes() {
+        match byte {
+            b' ' => encoded.push('_'),
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'!' | b'*' | b'+' | b'-' | b'/' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("={byte:02X}")),
+        }
+    }
+    encoded.push_str("?=");
+
+    encoded
+}
@@ -0,0 +1,221 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use parse_display::Display;
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DisplayFromStr};
+
+use furrow::{entries::Job, queue::Queue, Id, Repository};
+
+use super::{Error, Params, Ref, RefUpdate};
+
+/// The name of the [`Queue`] of pending job [`Run`]s.
+pub const QUEUE: &str = "jobs";
+
+/// The path of the file declaring the jobs, in the repository's commits.
+pub const CONFIG_PATH: &str = ".furrow/ci.toml";
+
+/// The name of the directory holding the [`Results`] of the jobs per commit, in the repository.
+pub const RESULTS_PATH: &str = "ci";
+
+/// A pending run of a job on a commit.
+#[serde_as]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Run {
+    /// The repository the commit is in.
+    pub id: Id,
+
+    /// The commit to run the job on.
+    #[serde_as(as = "DisplayFromStr")]
+    pub commit: git2::Oid,
+
+    /// The full name of the reference which triggered the run.
+    pub refname: String,
+
+    /// The job to run.
+    pub job: Job,
+}
+
+/// The results of the jobs run on a single commit, by job name.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Results(pub BTreeMap<String, JobResult>);
+
+/// The result of a single job run.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct JobResult {
+    /// The current state of the run.
+    pub state: State,
+
+    /// The full name of the reference which triggered the run.
+    pub refname: String,
+
+    /// The UNIX timestamp, in seconds, at which the run started.
+    pub started: Option<u64>,
+
+    /// The UNIX timestamp, in seconds, at which the run finished.
+    pub finished: Option<u64>,
+
+    /// The combined output of the commands, possibly truncated.
+    pub output: String,
+}
+
+/// The state of a job run.
+#[derive(Debug, Display, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[display(style = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum State {
+    /// The run is waiting to be picked by the server.
+    Pending,
+
+    /// The run is in progress.
+    Running,
+
+    /// All the commands succeeded.
+    Success,
+
+    /// One of the commands failed, or the job timed out.
+    Failure,
+
+    /// The run couldn't be carried out by the server.
+    Error,
+}
+
+impl Results {
+    /// Compute the path of the results file for the `commit` of the repository
+    /// pointed by the [`Id`] in the `storage` path.
+    pub fn path(storage: &Path, id: &Id, commit: git2::Oid) -> PathBuf {
+        id.to_path(storage)
+            .join(RESULTS_PATH)
+            .join(format!("{commit}.json"))
+    }
+
+    /// Load the results from the `path`, or empty ones if non-existant.
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        match std::fs::read(path) {
+            Ok(content) => Ok(serde_json::from_slice(&content)?),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Atomically save the results to the `path`.
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let temporary = path.with_extension("json.tmp");
+        std::fs::write(&temporary, serde_json::to_vec_pretty(self)?)?;
+        std::fs::rename(temporary, path).map_err(Into::into)
+    }
+}
+
+/// The jobs declared in the repository's [`CONFIG_PATH`].
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Config {
+    #[serde(default)]
+    jobs: Vec<Job>,
+}
+
+/// Enqueue a run of each of the `jobs` and of the ones declared in the pushed commits,
+/// for each of the matching `updates`.
+pub fn enqueue(
+    params: &Params,
+    repository: &Repository,
+    updates: &[RefUpdate],
+    jobs: &[Job],
+) -> Result<(), Error> {
+    let queue = Queue::<Run>::new(&params.storage, QUEUE);
+
+    for update in updates.iter().filter(|update| !update.newrev.is_zero()) {
+        let Ok(commit) = repository
+            .find_object(update.newrev, None)
+            .and_then(|object| object.peel_to_commit())
+        else {
+            continue;
+        };
+
+        let mut all = jobs.to_vec();
+        for job in declared(repository, &commit)? {
+            if all.iter().any(|other| other.name == job.name) {
+                println!(
+                    "hint: The job `{}` from `{CONFIG_PATH}` is already declared by the repository, ignoring",
+                    job.name
+                );
+            } else {
+                all.push(job);
+            }
+        }
+
+        let refname = update.refname.to_string();
+        let all: Vec<_> = all
+            .into_iter()
+            .filter(|job| match &job.refs {
+                Some(pattern) => pattern.is_match(&refname),
+                None => matches!(update.refname, Ref::Branch(_)),
+            })
+            .collect();
+        if all.is_empty() {
+            continue;
+        }
+
+        let path = Results::path(&params.storage, &params.id, commit.id());
+        let mut results = Results::load(&path)?;
+
+        for job in &all {
+            results.0.insert(
+                job.name.clone(),
+                JobResult {
+                    state: State::Pending,
+                    refname: refname.clone(),
+                    started: None,
+                    finished: None,
+                    output: String::new(),
+                },
+            );
+        }
+
+        // Save the results before queuing, not to overwrite the state of a picked run.
+        results.save(&path)?;
+
+        for job in all {
+            println!(
+                "hint: Queued job `{}` on `{:.7}` for `{refname}`",
+                job.name,
+                commit.id().to_string()
+            );
+
+            queue.push(Run {
+                id: params.id.clone(),
+                commit: commit.id(),
+                refname: refname.clone(),
+                job,
+            })?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Read the jobs declared in the [`CONFIG_PATH`] of the `commit`, if any.
+fn declared(repository: &Repository, commit: &git2::Commit) -> Result<Vec<Job>, Error> {
+    let Ok(entry) = commit.tree()?.get_path(Path::new(CONFIG_PATH)) else {
+        return Ok(Vec::new());
+    };
+    let blob = entry.to_object(repository)?.peel_to_blob()?;
+
+    match std::str::from_utf8(blob.content())
+        .map_err(|err| err.to_string())
+        .and_then(|content| toml::from_str::<Config>(content).map_err(|err| err.to_string()))
+    {
+        Ok(config) => Ok(config.jobs),
+        Err(err) => {
+            println!("hint: Ignoring the malformed `{CONFIG_PATH}`: {err}");
+
+            Ok(Vec::new())
+        }
+    }
+}
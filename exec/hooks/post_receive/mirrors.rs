@@ -0,0 +1,133 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use furrow::{entries::Mirror, queue::Queue, Id};
+
+use super::{Error, Params, RefUpdate};
+
+/// The name of the [`Queue`] of pending mirror [`Sync`]s.
+pub const QUEUE: &str = "mirrors";
+
+/// The name of the file holding the [`Status`] of the mirrors, in the repository.
+pub const STATUS_PATH: &str = "mirrors.json";
+
+/// A pending synchronization of references to a mirror.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Sync {
+    /// The repository to push the references from.
+    pub id: Id,
+
+    /// The mirror to push the references to.
+    pub mirror: Mirror,
+
+    /// The full names of the references to synchronize.
+    pub refs: Vec<String>,
+}
+
+/// The synchronization status of each of the mirrors of a repository, by URL.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Status(pub HashMap<String, MirrorStatus>);
+
+/// The synchronization status of a single mirror.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct MirrorStatus {
+    /// The UNIX timestamp, in seconds, of the last synchronization attempt.
+    pub last_attempt: u64,
+
+    /// The UNIX timestamp, in seconds, of the last successful synchronization.
+    pub last_success: Option<u64>,
+
+    /// The error of the last synchronization attempt, if it failed.
+    pub error: Option<String>,
+}
+
+impl Status {
+    /// Compute the path of the status file for the repository pointed by the [`Id`] in the `storage` path.
+    pub fn path(storage: &Path, id: &Id) -> PathBuf {
+        id.to_path(storage).join(STATUS_PATH)
+    }
+
+    /// Load the status from the `path`, or an empty one if non-existant.
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        load(path)
+    }
+
+    /// Atomically save the status to the `path`.
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        save(self, path)
+    }
+}
+
+impl MirrorStatus {
+    /// Load the status from the `path`, or an empty one if non-existant.
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        load(path)
+    }
+
+    /// Atomically save the status to the `path`.
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        save(self, path)
+    }
+}
+
+/// Load a status from the `path`, or an empty one if non-existant.
+fn load<T: Default + DeserializeOwned>(path: &Path) -> Result<T, Error> {
+    match std::fs::read(path) {
+        Ok(content) => Ok(serde_json::from_slice(&content)?),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(T::default()),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Atomically save the `status` to the `path`, through a temporary file.
+fn save<T: Serialize>(status: &T, path: &Path) -> Result<(), Error> {
+    let temporary = path.with_extension("json.tmp");
+
+    std::fs::write(&temporary, serde_json::to_vec_pretty(status)?)?;
+    std::fs::rename(temporary, path).map_err(Into::into)
+}
+
+/// Enqueue the synchronization of the `updates` to the `mirrors`,
+/// and hint about the mirrors which failed to synchronize previously.
+pub fn enqueue(params: &Params, updates: &[RefUpdate], mirrors: &[Mirror]) -> Result<(), Error> {
+    let queue = Queue::<Sync>::new(&params.storage, QUEUE);
+    let status = Status::load(&Status::path(&params.storage, &params.id))?;
+
+    for mirror in mirrors {
+        let refs: Vec<_> = updates
+            .iter()
+            .map(|update| update.refname.to_string())
+            .filter(|refname| {
+                mirror
+                    .refs
+                    .as_ref()
+                    .is_none_or(|pattern| pattern.is_match(refname))
+            })
+            .collect();
+
+        if let Some(err) = status
+            .0
+            .get(mirror.url.as_str())
+            .and_then(|status| status.error.as_ref())
+        {
+            println!(
+                "hint: The last synchronization to `{}` failed: {err}",
+                mirror.url
+            );
+        }
+
+        if !refs.is_empty() {
+            queue.push(Sync {
+                id: params.id.clone(),
+                mirror: mirror.clone(),
+                refs,
+            })?;
+        }
+    }
+
+    Ok(())
+}
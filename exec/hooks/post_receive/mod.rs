@@ -0,0 +1,90 @@
+use std::io;
+
+use clap::Parser;
+use futures::{io::AllowStdIo, TryStreamExt};
+
+use furrow::{
+    entries::{Entry, Repositories},
+    Repository,
+};
+
+use super::{Error, Params, Ref, RefUpdate};
+
+pub mod emails;
+pub mod jobs;
+pub mod mirrors;
+pub mod webhooks;
+
+/// The post-receive hook runs after the entire process is completed
+/// and can be used to update other services or notify users.
+/// It takes the same stdin data as the pre-receive hook.
+/// Examples include emailing a list, notifying a continuous integration server,
+/// or updating a ticket-tracking system – you can even parse the commit messages
+/// to see if any tickets need to be opened, modified, or closed.
+/// This script can’t stop the push process, but the client doesn’t disconnect until it has completed,
+/// so be careful if you try to do anything that may take a long time.
+///
+/// see https://git-scm.com/book/en/v2/Customizing-Git-Git-Hooks#_post_receive
+#[derive(Debug, Parser)]
+pub struct PostReceive {
+    #[command(flatten)]
+    params: Params,
+}
+
+impl PostReceive {
+    pub async fn run(self) -> Result<(), Error> {
+        let updates: Vec<_> = RefUpdate::from_io(AllowStdIo::new(io::stdin()))
+            .try_collect()
+            .await?;
+
+        if !self.params.id.is_authority() {
+            self.notify(&updates)?;
+        }
+
+        println!("success: Successfully updated refs :: ✓");
+
+        Ok(())
+    }
+
+    /// Queue the notifications of the `updates` to the configured services,
+    /// to be processed by the server in the background.
+    fn notify(&self, updates: &[RefUpdate]) -> Result<(), Error> {
+        let Params { storage, id, .. } = &self.params;
+
+        let repository = Repository::open_from_hook(storage, id)?;
+        let repositories = Repositories::load(&Repository::open(storage, &id.to_authority())?)?;
+        let Some(spec) = repositories.get(id.repository()) else {
+            return Ok(());
+        };
+
+        let webhooks: Vec<_> = repositories
+            .webhooks
+            .iter()
+            .chain(&spec.webhooks)
+            .cloned()
+            .collect();
+        if !webhooks.is_empty() {
+            webhooks::enqueue(&self.params, &repository, updates, &webhooks)?;
+        }
+
+        if !spec.mirrors.is_empty() {
+            mirrors::enqueue(&self.params, updates, &spec.mirrors)?;
+        }
+
+        if !self.params.options.ci_skip() {
+            jobs::enqueue(&self.params, &repository, updates, &spec.jobs)?;
+        }
+
+        if self.params.options.notify() {
+            emails::enqueue(
+                &self.params,
+                &repository,
+                updates,
+                &repositories.notifications,
+                &spec.notifications,
+            )?;
+        }
+
+        Ok(())
+    }
+}
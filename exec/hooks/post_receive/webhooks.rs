@@ -0,0 +1,158 @@
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use furrow::{
+    entries::{Webhook, WebhookEvent},
+    queue::Queue,
+    Repository,
+};
+
+use super::{Error, Params, Ref, RefUpdate};
+
+/// The name of the [`Queue`] of pending webhook [`Delivery`]s.
+pub const QUEUE: &str = "webhooks";
+
+/// The maximum count of commits listed per reference in the payloads.
+const MAX_COMMITS: usize = 20;
+
+/// A pending delivery of a payload to a webhook.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Delivery {
+    /// The URL the payload is `POST`ed to.
+    pub url: String,
+
+    /// The event the payload describes.
+    pub event: WebhookEvent,
+
+    /// The HMAC-SHA256 signature of the payload, formatted as `sha256=<hex>`.
+    pub signature: Option<String>,
+
+    /// The JSON payload.
+    pub payload: String,
+}
+
+#[derive(Serialize)]
+struct Payload<'p> {
+    event: WebhookEvent,
+    repository: String,
+    pusher: String,
+    options: Vec<&'p str>,
+    updates: Vec<UpdatePayload>,
+}
+
+#[derive(Serialize)]
+struct UpdatePayload {
+    #[serde(rename = "ref")]
+    refname: String,
+    before: String,
+    after: String,
+    commits: Vec<CommitPayload>,
+}
+
+#[derive(Serialize)]
+struct CommitPayload {
+    id: String,
+    summary: String,
+    author: String,
+    timestamp: i64,
+}
+
+/// Compute the [`WebhookEvent`] corresponding to the `update`.
+fn event(update: &RefUpdate) -> WebhookEvent {
+    match &update.refname {
+        _ if update.newrev.is_zero() => WebhookEvent::Delete,
+        Ref::Branch(_) => WebhookEvent::Push,
+        Ref::Tag(_) => WebhookEvent::Tag,
+        Ref::Other(_) => WebhookEvent::Ref,
+    }
+}
+
+/// Enqueue the deliveries of the `updates` to the `webhooks`, one per webhook and event.
+pub fn enqueue(
+    params: &Params,
+    repository: &Repository,
+    updates: &[RefUpdate],
+    webhooks: &[Webhook],
+) -> Result<(), Error> {
+    let queue = Queue::<Delivery>::new(&params.storage, QUEUE);
+
+    let events = [
+        WebhookEvent::Push,
+        WebhookEvent::Tag,
+        WebhookEvent::Ref,
+        WebhookEvent::Delete,
+    ];
+    let push = updates;
+    for event in events {
+        let updates = updates
+            .iter()
+            .filter(|update| self::event(update) == event)
+            .collect::<Vec<_>>();
+
+        if updates.is_empty() || !webhooks.iter().any(|webhook| webhook.accepts(event)) {
+            continue;
+        }
+
+        let payload = serde_json::to_string(&Payload {
+            event,
+            repository: params.id.to_string(),
+            pusher: params.key.fingerprint(Default::default()).to_string(),
+            options: params.options.raw().collect(),
+            updates: updates
+                .into_iter()
+                .map(|update| {
+                    Ok(UpdatePayload {
+                        refname: update.refname.to_string(),
+                        before: update.oldrev.to_string(),
+                        after: update.newrev.to_string(),
+                        commits: commits(repository, update, push)?,
+                    })
+                })
+                .collect::<Result<_, Error>>()?,
+        })?;
+
+        for webhook in webhooks.iter().filter(|webhook| webhook.accepts(event)) {
+            let signature = webhook.secret.as_ref().map(|secret| {
+                let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+                    .expect("HMAC can take a key of any size");
+                mac.update(payload.as_bytes());
+
+                format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+            });
+
+            queue.push(Delivery {
+                url: webhook.url.to_string(),
+                event,
+                signature,
+                payload: payload.clone(),
+            })?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Summarize the commits introduced by the `update`, most recent first.
+fn commits(
+    repository: &Repository,
+    update: &RefUpdate,
+    push: &[RefUpdate],
+) -> Result<Vec<CommitPayload>, Error> {
+    update
+        .commits(repository, push)?
+        .into_iter()
+        .take(MAX_COMMITS)
+        .map(|oid| {
+            let commit = repository.find_commit(oid)?;
+            let author = commit.author();
+
+            Ok(CommitPayload {
+                id: oid.to_string(),
+                summary: commit.summary().unwrap_or_default().into(),
+                author: author.to_string(),
+                timestamp: author.when().seconds(),
+            })
+        })
+        .collect()
+}
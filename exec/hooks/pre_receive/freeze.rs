@@ -0,0 +1,38 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ssh_key::PublicKey;
+
+use furrow::entries::{Freeze, Keychain};
+
+use super::{Error, RefUpdate};
+
+/// Check that none of the `freezes` are active for the `update`,
+/// unless the `key` is allowed to break the glass.
+pub fn check<'f>(
+    update: &RefUpdate,
+    freezes: impl IntoIterator<Item = &'f Freeze>,
+    keychain: &Keychain,
+    key: &PublicKey,
+) -> Result<(), Error> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs() as i64)
+        .unwrap_or_default();
+
+    for freeze in freezes.into_iter().filter(|freeze| freeze.is_active(now)) {
+        if freeze
+            .break_glass
+            .iter()
+            .any(|owner| keychain.owns(owner, key))
+        {
+            println!(
+                "hint: Ref `{}` is frozen ({freeze}), bypassing with a break-glass key.",
+                update.refname
+            );
+        } else {
+            return Err(Error::Frozen(update.refname.clone(), freeze.clone()));
+        }
+    }
+
+    Ok(())
+}
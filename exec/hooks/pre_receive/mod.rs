@@ -0,0 +1,188 @@
+use std::io;
+
+use clap::Parser;
+use futures::{io::AllowStdIo, TryStreamExt};
+use ssh_key::PublicKey;
+
+use super::{changes, Error, Params, Ref, RefUpdate};
+use furrow::{
+    authority,
+    entries::{Entry, Keychain, RefConfig, Repositories, Spec},
+    id::Kind,
+    proposal,
+    status::{self, Statuses},
+    Repository,
+};
+
+mod freeze;
+mod paths;
+pub mod secrets;
+
+/// The first script to run when handling a push from a client is pre-receive.
+/// It takes a list of references that are being pushed from stdin;
+/// if it exits non-zero, none of them are accepted.
+/// You can use this hook to do things like make sure none of the updated references are non-fast-forwards,
+/// or to do access control for all the refs and files they’re modifying with the push.
+///
+/// see https://git-scm.com/book/en/v2/Customizing-Git-Git-Hooks#_pre_receive
+#[derive(Debug, Parser)]
+pub struct PreReceive {
+    #[command(flatten)]
+    params: Params,
+}
+
+impl PreReceive {
+    pub async fn run(&self) -> Result<(), Error> {
+        self.params
+            .options
+            .validate()
+            .map_err(Error::IllegalPushOption)?;
+
+        RefUpdate::from_io(AllowStdIo::new(io::stdin()))
+            .try_for_each(|refupdate| self.receive(refupdate))
+            .await
+    }
+
+    async fn receive(&self, update: RefUpdate) -> Result<(), Error> {
+        let Params {
+            storage, id, key, ..
+        } = &self.params;
+
+        let repository = Repository::open_from_hook(storage, id)?;
+
+        let is_ff = update.is_ff(&repository)?;
+        let is_head = update.is_head(&repository)?;
+        let is_delete = update.is_delete();
+
+        match id.kind() {
+            Kind::GlobalAuthority | Kind::LocalAuthority => {
+                if is_delete {
+                    return if is_head {
+                        Err(Error::DeleteRef(update.refname))
+                    } else {
+                        // If we allow delete, don't check anything else
+                        Ok(())
+                    };
+                }
+
+                if !is_ff && is_head {
+                    return Err(Error::NonFastForward(update.refname));
+                }
+
+                (|| {
+                    // Verify that entries in the repository are correctly formatted
+                    // and that the removed repositories are empty before allowing the push.
+                    let tree = repository.find_commit(update.newrev)?.tree()?;
+
+                    authority::verify(
+                        id.namespace(),
+                        |path| authority::read_tree(&repository, &tree, path),
+                        Some(storage),
+                        Some(&Repositories::load(&repository)?),
+                    )?;
+
+                    Ok::<_, Error>(())
+                })()
+                .map_err(|err| if !is_head { err.into_hint() } else { err })
+            }
+            Kind::Normal => {
+                let authority = Repository::open(storage, &id.to_authority())?;
+                let repositories = Repositories::load(&authority)?;
+                let keychain = Keychain::load(&authority)?;
+                let spec = repositories
+                    .get(id.repository())
+                    .expect("Major failure: The repository is not defined in it's authority repository, how did we get here in the first place ?");
+
+                let is_proposal = matches!(&update.refname, Ref::Other(name) if name.starts_with(proposal::FOR_PREFIX));
+
+                // Keys outside of the keychain, such as proposers, may only submit proposals.
+                if !is_proposal && !keychain.contains(key) {
+                    return Err(Error::ProposalOnly(update.refname));
+                }
+
+                match (&update.refname, &spec.branches, &spec.tags) {
+                    (Ref::Branch(name), Some(regex), _) if !regex.is_match(name) => {
+                        return Err(Error::IllegalRefName(name.into(), regex.clone()))?
+                    }
+                    (Ref::Tag(name), _, Some(regex)) if !regex.is_match(name) => {
+                        return Err(Error::IllegalRefName(name.into(), regex.clone()))?
+                    }
+                    _ => (),
+                }
+
+                let refconfig = match &update.refname {
+                    Ref::Branch(name) => spec.branch.get(name).cloned().unwrap_or_default(),
+                    Ref::Tag(_) => RefConfig::unprotected(),
+                    // The statuses and the proposals are only ever written by the server.
+                    Ref::Other(name)
+                        if name == status::NOTES_REF
+                            || name.starts_with(proposal::PROPOSALS_PREFIX) =>
+                    {
+                        return Err(Error::IllegalRef(update.refname))
+                    }
+                    // The proposals are stored by the `proc-receive` hook, only scan for leaks.
+                    Ref::Other(_) if is_proposal => {
+                        return secrets::scan(&repository, &update, &spec.secrets)
+                    }
+                    Ref::Other(name) => spec
+                        .refconfig(name)
+                        .cloned()
+                        .ok_or_else(|| Error::IllegalRef(update.refname.clone()))?,
+                };
+
+                freeze::check(
+                    &update,
+                    spec.freeze.iter().chain(&refconfig.freeze),
+                    &keychain,
+                    key,
+                )?;
+
+                if !refconfig.allow_delete && is_delete {
+                    return Err(Error::DeleteRef(update.refname));
+                }
+
+                if !refconfig.allow_force && !is_ff {
+                    return Err(Error::NonFastForward(update.refname));
+                }
+
+                if !refconfig.require_statuses.is_empty() && !is_delete {
+                    let commit = repository
+                        .find_object(update.newrev, None)?
+                        .peel_to_commit()?
+                        .id();
+                    let statuses = Statuses::load(&repository, commit)?;
+
+                    if let Some(context) = refconfig
+                        .require_statuses
+                        .iter()
+                        .find(|context| !statuses.is_success(context))
+                    {
+                        return Err(Error::MissingStatus(
+                            update.refname,
+                            context.clone(),
+                            commit,
+                        ));
+                    }
+                }
+
+                check_content(&repository, &update, spec, &keychain, key)
+            }
+        }
+    }
+}
+
+/// Check the content introduced by the `update` against the path rules
+/// and the secret scanning of the repository's [`Spec`].
+pub fn check_content(
+    repository: &Repository,
+    update: &RefUpdate,
+    spec: &Spec,
+    keychain: &Keychain,
+    key: &PublicKey,
+) -> Result<(), Error> {
+    if !spec.paths.is_empty() {
+        paths::check(repository, update, &spec.paths, keychain, key)?;
+    }
+
+    secrets::scan(repository, update, &spec.secrets)
+}
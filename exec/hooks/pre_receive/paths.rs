@@ -0,0 +1,134 @@
+use std::path::Path;
+
+use ssh_key::PublicKey;
+
+use furrow::{
+    entries::{Keychain, PathRule},
+    Repository,
+};
+
+use super::{Error, RefUpdate};
+
+/// Check that the paths modified by the `update` are owned by the `key`,
+/// as per the [`PathRule`]s of the repository.
+pub fn check(
+    repository: &Repository,
+    update: &RefUpdate,
+    rules: &[PathRule],
+    keychain: &Keychain,
+    key: &PublicKey,
+) -> Result<(), Error> {
+    for commit in update.commits(repository, &[])? {
+        let commit = repository.find_commit(commit)?;
+
+        for delta in super::changes(repository, &commit)?.deltas() {
+            let paths = [delta.old_file().path(), delta.new_file().path()];
+
+            for path in paths.into_iter().flatten().filter_map(Path::to_str) {
+                let Some(rule) = rules.iter().rev().find(|rule| rule.pattern.is_match(path)) else {
+                    continue;
+                };
+
+                if !rule.owners.iter().any(|owner| keychain.owns(owner, key)) {
+                    return Err(Error::ProtectedPath(
+                        path.into(),
+                        commit.id(),
+                        rule.pattern.clone(),
+                    ));
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        path::PathBuf,
+        time::{SystemTime, UNIX_EPOCH},
+    };
+
+    use furrow::{entries::Entry, proposal, Id};
+
+    use super::*;
+    use crate::hooks::Ref;
+
+    const KEY: &str =
+        "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIL5wpJU3TRZj+OZpGu0wFYV/VzEAHtRvGOgVOK+40Gfq";
+
+    fn repository() -> (PathBuf, Repository) {
+        let storage = std::env::temp_dir().join(format!(
+            "furrow-paths-{}-{}",
+            std::process::id(),
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .expect("The clock is before the epoch")
+                .as_nanos()
+        ));
+        let repository = Repository::init(&storage, &"repo.git".parse::<Id>().expect("Invalid id"))
+            .expect("Unable to init the repository");
+
+        (storage, repository)
+    }
+
+    /// Commit a `path` in the `repository`, pointed by the `reference`.
+    fn commit(repository: &Repository, reference: &str, path: &str) -> git2::Oid {
+        let blob = repository
+            .blob(b"content")
+            .expect("Unable to write the blob");
+        let mut tree = repository
+            .treebuilder(None)
+            .expect("Unable to build the tree");
+        tree.insert(path, blob, 0o100644)
+            .expect("Unable to insert the blob");
+        let tree = repository
+            .find_tree(tree.write().expect("Unable to write the tree"))
+            .expect("Unable to find the tree");
+        let signature =
+            git2::Signature::now("t", "t@example.com").expect("Unable to create the signature");
+
+        repository
+            .commit(
+                Some(reference),
+                &signature,
+                &signature,
+                "Commit",
+                &tree,
+                &[],
+            )
+            .expect("Unable to commit")
+    }
+
+    #[test]
+    fn proposed_commits_are_checked_on_push() {
+        let (storage, repository) = repository();
+        let key: PublicKey = KEY.parse().expect("Unable to parse the key");
+        let keychain = Keychain::parse(&format!("keys = [\"{KEY}\"]")).expect("Invalid keychain");
+        let rules = [PathRule {
+            pattern: "protected".parse().expect("Invalid pattern"),
+            owners: Vec::new(),
+        }];
+
+        // The commit was stored as a proposal, then pushed to a branch.
+        let commit = commit(
+            &repository,
+            &format!("{}1/head", proposal::PROPOSALS_PREFIX),
+            "protected",
+        );
+        let update = RefUpdate {
+            oldrev: git2::Oid::zero(),
+            newrev: commit,
+            refname: Ref::Branch("main".into()),
+        };
+
+        assert!(matches!(
+            check(&repository, &update, &rules, &keychain, &key),
+            Err(Error::ProtectedPath(path, id, _)) if path == "protected" && id == commit
+        ));
+
+        fs::remove_dir_all(storage).expect("Unable to clean up the repository");
+    }
+}
@@ -0,0 +1,176 @@
+use std::{collections::HashSet, path::Path, sync::LazyLock};
+
+use regex::Regex;
+
+use furrow::{
+    entries::{Pattern, Secrets, SecretsPolicy},
+    Repository,
+};
+
+use super::{Error, RefUpdate};
+
+/// The in-repository path of the allowlist file, listing path patterns
+/// in which findings are to be ignored, one per line.
+pub const ALLOWLIST_PATH: &str = ".furrow/secrets.allow";
+
+/// Blobs bigger than this size are not scanned.
+const MAX_BLOB_SIZE: usize = 1024 * 1024;
+
+/// The built-in rules applied to the pushed content.
+static RULES: LazyLock<Vec<(&'static str, Regex)>> = LazyLock::new(|| {
+    [
+        (
+            "private key",
+            r"-----BEGIN ([A-Z0-9]+ )*PRIVATE KEY( BLOCK)?-----",
+        ),
+        ("AWS access key", r"\b(AKIA|ASIA)[0-9A-Z]{16}\b"),
+        ("GitHub token", r"\bgh[pousr]_[A-Za-z0-9]{36,}\b"),
+        ("GitHub token", r"\bgithub_pat_[A-Za-z0-9_]{22,}\b"),
+        ("GitLab token", r"\bglpat-[A-Za-z0-9_-]{20,}\b"),
+        ("Slack token", r"\bxox[abposr]-[A-Za-z0-9-]{10,}\b"),
+        ("Stripe key", r"\b[rs]k_live_[A-Za-z0-9]{24,}\b"),
+        ("Google API key", r"\bAIza[0-9A-Za-z_-]{35}\b"),
+    ]
+    .into_iter()
+    .map(|(name, regex)| (name, Regex::new(regex).expect("The regex was malformed")))
+    .collect()
+});
+
+/// A secret found in pushed content.
+#[derive(Debug)]
+pub struct Finding {
+    rule: String,
+    path: String,
+    line: usize,
+    commit: git2::Oid,
+}
+
+impl std::fmt::Display for Finding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} in `{}` at line {}, in commit {}",
+            self.rule, self.path, self.line, self.commit
+        )
+    }
+}
+
+/// A non-empty collection of [`Finding`]s.
+#[derive(Debug)]
+pub struct Findings(Vec<Finding>);
+
+impl std::fmt::Display for Findings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Potential secrets found in the pushed content:")?;
+
+        for finding in &self.0 {
+            write!(f, "\n  - {finding}")?;
+        }
+
+        write!(
+            f,
+            "\nIf these are false positives, list their paths in `{ALLOWLIST_PATH}` in a prior push."
+        )
+    }
+}
+
+/// Scan the blobs introduced by the `update` for secrets, and act according to the [`Secrets`] configuration.
+pub fn scan(repository: &Repository, update: &RefUpdate, config: &Secrets) -> Result<(), Error> {
+    if config.policy == SecretsPolicy::Ignore || update.is_delete() {
+        return Ok(());
+    }
+
+    // Read the allowlist from the current tip of the ref, or of the default branch for new refs,
+    // never from the pushed commits which could otherwise allowlist their own secrets.
+    let base = if update.oldrev.is_zero() {
+        repository.head().ok().and_then(|head| head.target())
+    } else {
+        Some(update.oldrev)
+    };
+    let allowlist = base
+        .map(|base| allowlist(repository, base))
+        .transpose()?
+        .unwrap_or_default();
+    let mut scanned = HashSet::new();
+    let mut findings = Vec::new();
+
+    for commit in update.commits(repository, &[])? {
+        let commit = repository.find_commit(commit)?;
+        for delta in super::changes(repository, &commit)?.deltas() {
+            let file = delta.new_file();
+            let Some(path) = file.path().and_then(Path::to_str) else {
+                continue;
+            };
+
+            if file.id().is_zero()
+                || !scanned.insert(file.id())
+                || allowlist.iter().any(|pattern| pattern.is_match(path))
+            {
+                continue;
+            }
+
+            let blob = repository.find_blob(file.id())?;
+            if blob.is_binary() || blob.size() > MAX_BLOB_SIZE {
+                continue;
+            }
+
+            let content = String::from_utf8_lossy(blob.content());
+            for (idx, line) in content.lines().enumerate() {
+                let rule = RULES
+                    .iter()
+                    .find(|(_, regex)| regex.is_match(line))
+                    .map(|(name, _)| name.to_string())
+                    .or_else(|| {
+                        config
+                            .patterns
+                            .iter()
+                            .find(|regex| regex.is_match(line))
+                            .map(|regex| format!("pattern `{regex}`"))
+                    });
+
+                if let Some(rule) = rule {
+                    findings.push(Finding {
+                        rule,
+                        path: path.into(),
+                        line: idx + 1,
+                        commit: commit.id(),
+                    });
+                }
+            }
+        }
+    }
+
+    if findings.is_empty() {
+        return Ok(());
+    }
+
+    let err = Error::SecretLeak(Findings(findings));
+    match config.policy {
+        SecretsPolicy::Warn => {
+            err.into_hint().emit();
+
+            Ok(())
+        }
+        _ => Err(err),
+    }
+}
+
+/// Read the allowlist from the tree of the `commit`, if any.
+fn allowlist(repository: &Repository, commit: git2::Oid) -> Result<Vec<Pattern>, Error> {
+    let tree = repository.find_commit(commit)?.tree()?;
+    let entry = match tree.get_path(Path::new(ALLOWLIST_PATH)) {
+        Err(err) if err.code() == git2::ErrorCode::NotFound => return Ok(Vec::new()),
+        other => other?,
+    };
+    let blob = entry.to_object(repository)?.peel_to_blob()?;
+
+    String::from_utf8_lossy(blob.content())
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            line.parse()
+                .map_err(|err| Error::IllegalPattern(ALLOWLIST_PATH, line.into(), err))
+        })
+        .collect()
+}
@@ -0,0 +1,178 @@
+use std::{
+    io,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use clap::Parser;
+
+use furrow::{
+    proposal::{self, Proposal, State},
+    Repository,
+};
+
+use super::{io::PushOptions, Error, Params, Ref, RefUpdate};
+use crate::pktline;
+
+/// The proc-receive hook is run by `git-receive-pack` in place of updating the references
+/// matching `receive.procReceiveRefs`, here the `refs/for/<branch>` references.
+/// It takes the commands from stdin and reports the result of each on stdout using the pkt-line protocol,
+/// with the references it updated instead, here the `refs/proposals/<number>/head` references.
+///
+/// see https://git-scm.com/docs/githooks#proc-receive
+#[derive(Debug, Parser)]
+pub struct ProcReceive {
+    #[command(flatten)]
+    params: Params,
+}
+
+impl ProcReceive {
+    pub async fn run(self) -> Result<(), Error> {
+        // The stdout being used for the protocol, errors are reported through stderr.
+        if let Err(err) = self.process() {
+            eprintln!("error: {err}");
+
+            std::process::exit(1);
+        }
+
+        Ok(())
+    }
+
+    fn process(&self) -> Result<(), Error> {
+        let mut stdin = io::stdin().lock();
+        let mut stdout = io::stdout().lock();
+
+        // Negotiate the protocol version, and receive the push options.
+        let version = pktline::read(&mut stdin)?;
+        if !version
+            .first()
+            .is_some_and(|line| line.starts_with("version=1"))
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Unsupported version of the proc-receive protocol",
+            )
+            .into());
+        }
+        pktline::write(&mut stdout, &["version=1\0push-options".into()])?;
+
+        let updates = pktline::read(&mut stdin)?
+            .iter()
+            .map(|line| line.parse::<RefUpdate>().map_err(Error::RefUpdateParse))
+            .collect::<Result<Vec<_>, _>>()?;
+        let options = PushOptions::new(pktline::read(&mut stdin)?);
+
+        let repository = Repository::open_from_hook(&self.params.storage, &self.params.id)?;
+
+        let mut report = Vec::new();
+        for update in updates {
+            match self.propose(&repository, &update, &options) {
+                Ok(mut lines) => report.append(&mut lines),
+                Err(err) => report.push(format!("ng {} {err}", update.refname)),
+            }
+        }
+
+        pktline::write(&mut stdout, &report).map_err(Into::into)
+    }
+
+    /// Create or update a proposal with the `update`, returning the lines reporting the updated reference.
+    fn propose(
+        &self,
+        repository: &Repository,
+        update: &RefUpdate,
+        options: &PushOptions,
+    ) -> Result<Vec<String>, Error> {
+        let Params { id, key, .. } = &self.params;
+
+        let name = update.refname.to_string();
+        let Some(target) = name.strip_prefix(proposal::FOR_PREFIX) else {
+            return Err(Error::IllegalRef(update.refname.clone()));
+        };
+
+        if id.is_authority() {
+            return Err(Error::IllegalRef(update.refname.clone()));
+        }
+        if update.newrev.is_zero() {
+            return Err(Error::DeleteRef(update.refname.clone()));
+        }
+
+        if repository
+            .find_reference(&format!("refs/heads/{target}"))
+            .is_err()
+        {
+            return Err(Error::MissingTarget(Ref::Branch(target.into())));
+        }
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs() as i64;
+        let author = key.fingerprint(Default::default()).to_string();
+        let topic = options.topic();
+
+        // Pushing with the same topic updates the author's open proposal, if any.
+        let existing = match topic {
+            Some(topic) => Proposal::list(repository)?.into_iter().find(|proposal| {
+                proposal.state == State::Open
+                    && proposal.target == target
+                    && proposal.author == author
+                    && proposal.topic.as_deref() == Some(topic)
+            }),
+            None => None,
+        };
+
+        let (proposal, action) = match existing {
+            Some(mut proposal) => {
+                proposal.updated = now;
+                proposal.save(repository)?;
+
+                (proposal, "Updated")
+            }
+            None => {
+                let mut proposal = Proposal {
+                    number: 0,
+                    target: target.into(),
+                    topic: topic.map(Into::into),
+                    author,
+                    created: now,
+                    updated: now,
+                    state: State::Open,
+                    series: None,
+                };
+                proposal.create(repository)?;
+
+                (proposal, "Created")
+            }
+        };
+
+        let refname = proposal.refname();
+        let oldrev = repository
+            .refname_to_id(&refname)
+            .unwrap_or_else(|_| git2::Oid::zero());
+        repository.reference(
+            &refname,
+            update.newrev,
+            true,
+            &format!("proposal: {}", update.refname),
+        )?;
+
+        eprintln!(
+            "hint: {action} proposal #{} for `{}`, at `{refname}`.",
+            proposal.number, proposal.target
+        );
+
+        let mut lines = vec![
+            format!("ok {}", update.refname),
+            format!("option refname {refname}"),
+            format!("option old-oid {oldrev}"),
+            format!("option new-oid {}", update.newrev),
+        ];
+        if !oldrev.is_zero()
+            && oldrev != update.newrev
+            && !repository.graph_descendant_of(update.newrev, oldrev)?
+        {
+            lines.push("option forced-update".into());
+        }
+
+        Ok(lines)
+    }
+}
@@ -0,0 +1,576 @@
+//! The server side of the _Git LFS_ pure SSH transfer protocol,
+//! serving the LFS objects and file locks of the repositories.
+//!
+//! see https://github.com/git-lfs/git-lfs/blob/main/docs/proposals/ssh_adapter.md
+
+use std::{
+    io::{self, Read, Write},
+    path::PathBuf,
+};
+
+use chrono::{SecondsFormat, Utc};
+use clap::{Parser, ValueEnum};
+use color_eyre::eyre;
+use parse_display::{Display, FromStr};
+use ssh_key::PublicKey;
+
+use furrow::{
+    entries::{Entry, Global, Keychain},
+    lfs::{self, Lock, Locks, Objects},
+    Id, Repository,
+};
+
+use crate::{
+    hooks::io::{PUBLIC_KEY_ENV, REPOSITORY_ID_ENV, STORAGE_PATH_ENV},
+    pktline::{self, Packet, MAX_PAYLOAD},
+};
+
+/// The default count of locks listed at once.
+const DEFAULT_LIMIT: usize = 100;
+
+/// Execute as `git-lfs-transfer`, spawned by the server for the client's LFS requests.
+#[derive(Debug, Parser)]
+pub struct Transfer {
+    #[arg(long, env = STORAGE_PATH_ENV)]
+    storage: PathBuf,
+
+    #[arg(long, env = REPOSITORY_ID_ENV)]
+    id: Id,
+
+    /// The public key of the client, unset for the anonymous downloads.
+    #[arg(long, env = PUBLIC_KEY_ENV)]
+    key: Option<PublicKey>,
+
+    /// The path of the repository, as requested by the client.
+    path: String,
+
+    /// The operation the client intends to perform.
+    operation: Operation,
+}
+
+/// The operation a client intends to perform, determining it's access.
+#[derive(Debug, Display, FromStr, Clone, Copy, PartialEq, Eq, ValueEnum)]
+#[display(style = "lowercase")]
+pub enum Operation {
+    Upload,
+    Download,
+}
+
+/// A request sent by the client, with it's arguments,
+/// the data following the arguments being left unread.
+struct Request {
+    command: String,
+    args: Vec<String>,
+    has_data: bool,
+}
+
+impl Request {
+    /// Read the next request from the `reader`, or [`None`] if the client hung-up.
+    fn read(reader: &mut impl Read) -> io::Result<Option<Self>> {
+        let command = match pktline::read_line(reader) {
+            Ok(Some(command)) => command,
+            Ok(None) => return Err(invalid("Received an empty request")),
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(err) => return Err(err),
+        };
+
+        let mut args = Vec::new();
+        let has_data = loop {
+            match pktline::read_packet(reader)? {
+                Packet::Data(arg) => args.push(
+                    String::from_utf8_lossy(&arg)
+                        .trim_end_matches('\n')
+                        .to_owned(),
+                ),
+                Packet::Delim => break true,
+                Packet::Flush => break false,
+            }
+        };
+
+        Ok(Some(Self {
+            command,
+            args,
+            has_data,
+        }))
+    }
+
+    /// Get the value of the argument named `key`, if any.
+    fn arg(&self, key: &str) -> Option<&str> {
+        self.args.iter().find_map(|arg| {
+            arg.strip_prefix(key)
+                .and_then(|value| value.strip_prefix('='))
+        })
+    }
+
+    /// Get the value of the `size` argument.
+    fn size(&self) -> Result<u64, Failure> {
+        self.arg("size")
+            .and_then(|size| size.parse().ok())
+            .ok_or_else(|| Failure(400, "Missing or invalid `size` argument".into()))
+    }
+}
+
+/// A failed request, reported to the client with a status code and a message.
+struct Failure(u16, String);
+
+impl From<io::Error> for Failure {
+    fn from(value: io::Error) -> Self {
+        Self(500, value.to_string())
+    }
+}
+
+/// A successful response to a request, with it's arguments and data lines.
+struct Response {
+    status: u16,
+    args: Vec<String>,
+    data: Option<Vec<String>>,
+}
+
+impl Response {
+    fn ok() -> Self {
+        Self {
+            status: 200,
+            args: Vec::new(),
+            data: None,
+        }
+    }
+
+    fn write(&self, writer: &mut impl Write) -> io::Result<()> {
+        let data = self.data.iter().flatten();
+
+        write_lines(
+            writer,
+            self.status,
+            &self.args,
+            self.data.is_some().then_some(data),
+        )
+    }
+}
+
+impl Transfer {
+    pub fn run(self) -> eyre::Result<()> {
+        let Self {
+            storage, id, key, ..
+        } = &self;
+
+        let repository = Repository::open(storage, id)?;
+        let keychain = Keychain::load(&Repository::open(storage, &id.to_authority())?)?;
+        let global = Global::load(&Repository::open(storage, &Id::global_authority())?)?;
+        let objects = Objects::new(&repository);
+
+        let mut stdin = io::stdin().lock();
+        let mut stdout = io::stdout().lock();
+
+        // Advertise our capabilities, and negotiate the protocol version.
+        pktline::write(&mut stdout, &["version=1".into()])?;
+        if pktline::read(&mut stdin)? != ["version 1"] {
+            write_lines(
+                &mut stdout,
+                400,
+                &[],
+                Some(["Unsupported protocol version".into()].iter()),
+            )?;
+            stdout.flush()?;
+
+            eyre::bail!("The client requested an unsupported protocol version");
+        }
+        Response::ok().write(&mut stdout)?;
+        stdout.flush()?;
+
+        let fingerprint = key
+            .as_ref()
+            .map(|key| key.fingerprint(Default::default()).to_string());
+        let session = Session {
+            operation: self.operation,
+            repository: &repository,
+            objects: &objects,
+            keychain: &keychain,
+            max_size: global.lfs.unwrap_or_default().max_size,
+            identity: key.as_ref().zip(fingerprint.as_deref()),
+        };
+
+        while let Some(request) = Request::read(&mut stdin)? {
+            let result = match request.command.split_once(' ') {
+                None if request.command == "quit" => {
+                    Response::ok().write(&mut stdout)?;
+                    stdout.flush()?;
+
+                    break;
+                }
+                None if request.command == "batch" => session.batch(&request, &mut stdin),
+                None if request.command == "lock" => session.lock(&request),
+                None if request.command == "list-lock" => session.list_locks(&request),
+                Some(("put-object", oid)) => session.put_object(&request, oid, &mut stdin),
+                Some(("verify-object", oid)) => session.verify_object(&request, oid),
+                Some(("get-object", oid)) => match session.get_object(oid, &mut stdout) {
+                    // The object has been streamed to the client along with the response.
+                    Ok(()) => continue,
+                    Err(err) => Err(err),
+                },
+                Some(("unlock", id)) => session.unlock(&request, id),
+                _ => Err(Failure(
+                    400,
+                    format!("Unknown command `{}`", request.command),
+                )),
+            };
+
+            match result {
+                Ok(response) => response.write(&mut stdout)?,
+                Err(Failure(status, message)) => {
+                    write_lines(&mut stdout, status, &[], Some([message].iter()))?
+                }
+            }
+            stdout.flush()?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The state of a transfer session, shared by the requests.
+struct Session<'s> {
+    operation: Operation,
+    repository: &'s Repository,
+    objects: &'s Objects,
+    keychain: &'s Keychain,
+    max_size: u64,
+    identity: Option<(&'s PublicKey, &'s str)>,
+}
+
+impl Session<'_> {
+    /// Require the session to be opened for upload by an authenticated client,
+    /// returning it's key and fingerprint.
+    fn require_upload(&self) -> Result<(&PublicKey, &str), Failure> {
+        if self.operation != Operation::Upload {
+            return Err(Failure(
+                403,
+                "The session has been opened for download only".into(),
+            ));
+        }
+
+        self.identity
+            .ok_or_else(|| Failure(401, "The client is not authenticated".into()))
+    }
+
+    fn batch(&self, request: &Request, reader: &mut impl Read) -> Result<Response, Failure> {
+        let objects = if request.has_data {
+            pktline::read(reader)?
+        } else {
+            Vec::new()
+        };
+
+        if request
+            .arg("hash-algo")
+            .is_some_and(|algo| algo != "sha256")
+        {
+            return Err(Failure(
+                409,
+                "Only the `sha256` hash algorithm is supported".into(),
+            ));
+        }
+
+        let data = objects
+            .iter()
+            .map(|line| {
+                let mut parts = line.split(' ');
+                let (Some(oid), Some(size)) = (
+                    parts.next().filter(|oid| lfs::is_oid(oid)),
+                    parts.next().and_then(|size| size.parse::<u64>().ok()),
+                ) else {
+                    return Err(Failure(400, format!("Invalid object `{line}`")));
+                };
+
+                let action = match (self.operation, self.objects.size(oid)?) {
+                    (Operation::Upload, Some(stored)) if stored == size => "noop",
+                    (Operation::Upload, _) => "upload",
+                    (Operation::Download, _) => "download",
+                };
+
+                Ok(format!("{oid} {size} {action}"))
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Response {
+            status: 200,
+            args: request
+                .arg("hash-algo")
+                .map(|algo| format!("hash-algo={algo}"))
+                .into_iter()
+                .collect(),
+            data: Some(data),
+        })
+    }
+
+    fn put_object(
+        &self,
+        request: &Request,
+        oid: &str,
+        reader: &mut impl Read,
+    ) -> Result<Response, Failure> {
+        // The object's data has to be consumed, whether it is stored or not,
+        // and is only stored up to it's announced size, if allowed.
+        let size = request.size();
+        let mut writer = match (
+            self.require_upload(),
+            lfs::is_oid(oid),
+            request.has_data,
+            &size,
+        ) {
+            (Ok(_), true, true, Ok(size)) if *size <= self.max_size => {
+                Some(self.objects.writer(oid)?)
+            }
+            _ => None,
+        };
+        let mut received = 0;
+        if request.has_data {
+            loop {
+                match pktline::read_packet(reader)? {
+                    Packet::Data(chunk) => {
+                        received += chunk.len() as u64;
+                        if size.as_ref().is_ok_and(|size| received > *size) {
+                            writer = None;
+                        }
+
+                        if let Some(writer) = &mut writer {
+                            writer.write_all(&chunk)?;
+                        }
+                    }
+                    Packet::Delim => (),
+                    Packet::Flush => break,
+                }
+            }
+        }
+
+        self.require_upload()?;
+        let size = size?;
+        if !lfs::is_oid(oid) || !request.has_data {
+            return Err(Failure(400, format!("Invalid object `{oid}`")));
+        }
+        if size > self.max_size {
+            return Err(Failure(
+                413,
+                format!(
+                    "The object `{oid}` exceeds the maximum size of {} bytes",
+                    self.max_size
+                ),
+            ));
+        }
+
+        let stored = match writer {
+            Some(writer) if received == size => writer.commit(size)?,
+            _ => false,
+        };
+        if !stored {
+            return Err(Failure(
+                400,
+                format!("The object `{oid}` doesn't match it's id or size"),
+            ));
+        }
+
+        Ok(Response::ok())
+    }
+
+    fn verify_object(&self, request: &Request, oid: &str) -> Result<Response, Failure> {
+        let size = request.size()?;
+
+        match lfs::is_oid(oid)
+            .then(|| self.objects.size(oid))
+            .transpose()?
+            .flatten()
+        {
+            Some(stored) if stored == size => Ok(Response::ok()),
+            _ => Err(Failure(404, format!("The object `{oid}` does not exist"))),
+        }
+    }
+
+    /// Stream the object to the `writer`, along with the response.
+    fn get_object(&self, oid: &str, writer: &mut impl Write) -> Result<(), Failure> {
+        let file = lfs::is_oid(oid)
+            .then(|| std::fs::File::open(self.objects.path(oid)))
+            .and_then(Result::ok)
+            .ok_or_else(|| Failure(404, format!("The object `{oid}` does not exist")))?;
+        let size = file.metadata()?.len();
+
+        pktline::write_packet(writer, &Packet::Data(b"status 200\n".to_vec()))?;
+        pktline::write_packet(writer, &Packet::Data(format!("size={size}\n").into_bytes()))?;
+        pktline::write_packet(writer, &Packet::Delim)?;
+
+        let mut file = io::BufReader::new(file);
+        let mut buffer = vec![0; MAX_PAYLOAD];
+        loop {
+            let n = file.read(&mut buffer)?;
+            if n == 0 {
+                break;
+            }
+
+            pktline::write_packet(writer, &Packet::Data(buffer[..n].to_vec()))?;
+        }
+
+        pktline::write_packet(writer, &Packet::Flush)?;
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    fn lock(&self, request: &Request) -> Result<Response, Failure> {
+        let (key, fingerprint) = self.require_upload()?;
+
+        let path = request
+            .arg("path")
+            .ok_or_else(|| Failure(400, "Missing `path` argument".into()))?;
+
+        let lock = Lock {
+            id: format!("{:016x}", rand::random::<u64>()),
+            path: path.into(),
+            locked_at: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+            owner: fingerprint.into(),
+            ownername: self.keychain.email(key).unwrap_or(fingerprint).into(),
+        };
+
+        let result = Locks::update(self.repository, |locks| {
+            match locks.0.iter().find(|existing| existing.path == lock.path) {
+                Some(existing) => Err(existing.clone()),
+                None => {
+                    locks.0.push(lock.clone());
+
+                    Ok(lock)
+                }
+            }
+        })?;
+
+        Ok(match result {
+            Ok(lock) => Response {
+                status: 201,
+                args: fields(&lock),
+                data: None,
+            },
+            Err(existing) => Response {
+                status: 409,
+                args: fields(&existing),
+                data: None,
+            },
+        })
+    }
+
+    fn list_locks(&self, request: &Request) -> Result<Response, Failure> {
+        let limit = request
+            .arg("limit")
+            .map(|limit| limit.parse::<usize>())
+            .transpose()
+            .map_err(|_| Failure(400, "Invalid `limit` argument".into()))?
+            .unwrap_or(DEFAULT_LIMIT)
+            .max(1);
+
+        let mut locks = Locks::load(self.repository)?.0;
+        locks.sort_by(|a, b| a.id.cmp(&b.id));
+        locks.retain(|lock| {
+            request.arg("path").is_none_or(|path| lock.path == path)
+                && request.arg("id").is_none_or(|id| lock.id == id)
+                && request
+                    .arg("cursor")
+                    .is_none_or(|cursor| *lock.id >= *cursor)
+        });
+
+        let mut args = Vec::new();
+        if let Some(next) = locks.get(limit) {
+            args.push(format!("next-cursor={}", next.id));
+        }
+
+        let data = locks
+            .iter()
+            .take(limit)
+            .flat_map(|lock| {
+                let owner = if self
+                    .identity
+                    .is_some_and(|(_, fingerprint)| lock.owner == fingerprint)
+                {
+                    "ours"
+                } else {
+                    "theirs"
+                };
+
+                [
+                    format!("lock {}", lock.id),
+                    format!("path {} {}", lock.id, lock.path),
+                    format!("locked-at {} {}", lock.id, lock.locked_at),
+                    format!("ownername {} {}", lock.id, lock.ownername),
+                    format!("owner {} {owner}", lock.id),
+                ]
+            })
+            .collect();
+
+        Ok(Response {
+            status: 200,
+            args,
+            data: Some(data),
+        })
+    }
+
+    fn unlock(&self, request: &Request, id: &str) -> Result<Response, Failure> {
+        let (key, fingerprint) = self.require_upload()?;
+
+        let force = request.arg("force") == Some("true");
+        let admin = self.keychain.contains(key);
+
+        Locks::update(self.repository, |locks| {
+            let index = locks
+                .0
+                .iter()
+                .position(|lock| lock.id == id)
+                .ok_or_else(|| Failure(404, format!("The lock `{id}` does not exist")))?;
+
+            let lock = &locks.0[index];
+            if lock.owner != fingerprint && !(force && admin) {
+                return Err(Failure(
+                    403,
+                    format!("The lock `{id}` is owned by {}", lock.ownername),
+                ));
+            }
+
+            Ok(Response {
+                status: 200,
+                args: fields(&locks.0.remove(index)),
+                data: None,
+            })
+        })?
+    }
+}
+
+/// Format the fields of the `lock`, as arguments of a response.
+fn fields(lock: &Lock) -> Vec<String> {
+    vec![
+        format!("id={}", lock.id),
+        format!("path={}", lock.path),
+        format!("locked-at={}", lock.locked_at),
+        format!("ownername={}", lock.ownername),
+    ]
+}
+
+/// Write a response with the `status`, `args` and `data` lines, if any, to the `writer`.
+fn write_lines<'l>(
+    writer: &mut impl Write,
+    status: u16,
+    args: &[String],
+    data: Option<impl Iterator<Item = &'l String>>,
+) -> io::Result<()> {
+    let line = |line: &str| Packet::Data(format!("{line}\n").into_bytes());
+
+    pktline::write_packet(writer, &line(&format!("status {status}")))?;
+    for arg in args {
+        pktline::write_packet(writer, &line(arg))?;
+    }
+
+    if let Some(data) = data {
+        pktline::write_packet(writer, &Packet::Delim)?;
+
+        for data in data {
+            pktline::write_packet(writer, &line(data))?;
+        }
+    }
+
+    pktline::write_packet(writer, &Packet::Flush)
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
@@ -1,24 +1,55 @@
-use clap::Parser;
+use clap::{error::ErrorKind, CommandFactory, Parser, Subcommand};
 use color_eyre::eyre;
 use tracing_subscriber::{fmt, prelude::*, EnvFilter};
 
 mod hooks;
+mod lfs;
+mod pktline;
 mod server;
+mod validate;
 
 #[derive(Debug, Parser)]
 #[command(multicall = true, rename_all = "kebab-case")]
 pub enum Cli {
     #[command(name = env!("CARGO_PKG_NAME"))]
-    Server(server::Server),
+    Furrow(Furrow),
 
     #[command(flatten)]
     Hooks(hooks::Hooks),
+
+    #[command(name = "git-lfs-transfer")]
+    LfsTransfer(lfs::Transfer),
+}
+
+/// The main command, starting the server unless a subcommand is supplied.
+#[derive(Debug, Parser)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+pub struct Furrow {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[command(flatten)]
+    server: Option<server::Server>,
+}
+
+#[derive(Debug, Subcommand)]
+#[command(rename_all = "kebab-case")]
+pub enum Command {
+    /// Validate the entries of an authority repository, as the server would on push.
+    Validate(validate::Validate),
 }
 
 #[tokio::main]
 async fn main() -> eyre::Result<()> {
     match Cli::parse() {
-        Cli::Server(server) => {
+        Cli::Furrow(Furrow {
+            command: Some(Command::Validate(validate)),
+            ..
+        }) => validate.run(),
+        Cli::Furrow(Furrow {
+            server: Some(server),
+            ..
+        }) => {
             // Set-up the pretty-printed error handler
             color_eyre::install()?;
 
@@ -32,5 +63,12 @@ async fn main() -> eyre::Result<()> {
             server.start().await
         }
         Cli::Hooks(hook) => hook.run().await,
+        Cli::LfsTransfer(transfer) => transfer.run(),
+        Cli::Furrow(_) => Furrow::command()
+            .error(
+                ErrorKind::MissingRequiredArgument,
+                "Either the server arguments or a subcommand are required",
+            )
+            .exit(),
     }
 }
@@ -0,0 +1,198 @@
+//! A minimal implementation of git's _pkt-line_ format, used by `git-receive-pack`
+//! to talk with the `proc-receive` hook, and by `git-lfs` over SSH.
+//!
+//! see https://git-scm.com/docs/protocol-common#_pkt_line_format
+
+use std::io::{self, Read, Write};
+
+/// The maximum length of a pkt-line, including it's 4 bytes length prefix.
+const MAX_LENGTH: usize = 65520;
+
+/// The maximum length of the payload of a pkt-line.
+pub const MAX_PAYLOAD: usize = MAX_LENGTH - 4;
+
+/// A single packet of the pkt-line format.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Packet {
+    /// A packet holding a payload.
+    Data(Vec<u8>),
+
+    /// A _delim-pkt_, separating sections of a message.
+    Delim,
+
+    /// A _flush-pkt_, terminating a message.
+    Flush,
+}
+
+/// Read a single packet from the `reader`.
+pub fn read_packet(reader: &mut impl Read) -> io::Result<Packet> {
+    let mut length = [0; 4];
+    reader.read_exact(&mut length)?;
+
+    let length = std::str::from_utf8(&length)
+        .ok()
+        .filter(|length| length.bytes().all(|byte| byte.is_ascii_hexdigit()))
+        .and_then(|length| usize::from_str_radix(length, 16).ok())
+        .filter(|length| *length <= 1 || (5..=MAX_LENGTH).contains(length))
+        .ok_or_else(|| invalid("Received an invalid pkt-line length"))?;
+
+    match length {
+        0 => Ok(Packet::Flush),
+        1 => Ok(Packet::Delim),
+        _ => {
+            let mut payload = vec![0; length - 4];
+            reader.read_exact(&mut payload)?;
+
+            Ok(Packet::Data(payload))
+        }
+    }
+}
+
+/// Read a textual packet from the `reader`, stripping it's trailing newline,
+/// or [`None`] if it is not a data packet.
+pub fn read_line(reader: &mut impl Read) -> io::Result<Option<String>> {
+    match read_packet(reader)? {
+        Packet::Data(payload) => {
+            let mut line =
+                String::from_utf8(payload).map_err(|_| invalid("Received a non-utf8 pkt-line"))?;
+            if line.ends_with('\n') {
+                line.pop();
+            }
+
+            Ok(Some(line))
+        }
+        Packet::Delim | Packet::Flush => Ok(None),
+    }
+}
+
+/// Read the pkt-lines from the `reader` until a _flush-pkt_,
+/// stripping their trailing newline.
+pub fn read(reader: &mut impl Read) -> io::Result<Vec<String>> {
+    let mut lines = Vec::new();
+
+    while let Some(line) = read_line(reader)? {
+        lines.push(line);
+    }
+
+    Ok(lines)
+}
+
+/// Write a single `packet` to the `writer`.
+pub fn write_packet(writer: &mut impl Write, packet: &Packet) -> io::Result<()> {
+    match packet {
+        Packet::Data(payload) if payload.len() > MAX_PAYLOAD => {
+            Err(invalid("The pkt-line exceeds the maximum length"))
+        }
+        Packet::Data(payload) => {
+            write!(writer, "{:04x}", payload.len() + 4)?;
+            writer.write_all(payload)
+        }
+        Packet::Delim => writer.write_all(b"0001"),
+        Packet::Flush => writer.write_all(b"0000"),
+    }
+}
+
+/// Write the `lines` as pkt-lines to the `writer`, followed by a _flush-pkt_.
+pub fn write(writer: &mut impl Write, lines: &[String]) -> io::Result<()> {
+    for line in lines {
+        write_packet(writer, &Packet::Data(format!("{line}\n").into_bytes()))?;
+    }
+
+    write_packet(writer, &Packet::Flush)?;
+    writer.flush()
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+
+    #[rstest]
+    #[case(b"0000", Packet::Flush)]
+    #[case(b"0001", Packet::Delim)]
+    #[case(b"0005a", Packet::Data(b"a".to_vec()))]
+    #[case(b"0009want\n", Packet::Data(b"want\n".to_vec()))]
+    #[case(b"0000ignored", Packet::Flush)]
+    fn it_reads_packets(#[case] input: &[u8], #[case] expected: Packet) {
+        assert_eq!(
+            read_packet(&mut &input[..]).expect("Unable to read the packet"),
+            expected
+        );
+    }
+
+    #[rstest]
+    // Reserved lengths, and empty data packets.
+    #[case(b"0002")]
+    #[case(b"0003")]
+    #[case(b"0004")]
+    // Lengths over the maximum.
+    #[case(b"fff1")]
+    #[case(b"ffff")]
+    // Malformed lengths.
+    #[case(b"zzzz")]
+    #[case(b"+005a")]
+    #[case(b"\xff\xff\xff\xff")]
+    // Truncated inputs.
+    #[case(b"")]
+    #[case(b"00")]
+    #[case(b"0009wa")]
+    fn it_denies_malformed_packets(#[case] input: &[u8]) {
+        assert!(
+            read_packet(&mut &input[..]).is_err(),
+            "The packet was malformed, but didn't error"
+        );
+    }
+
+    #[rstest]
+    #[case(b"0006a\n0005b0000", &["a", "b"])]
+    #[case(b"0006a\n0001", &["a"])]
+    #[case(b"0000", &[])]
+    fn it_reads_lines_until_flush(#[case] input: &[u8], #[case] expected: &[&str]) {
+        assert_eq!(
+            read(&mut &input[..]).expect("Unable to read the lines"),
+            expected
+        );
+    }
+
+    #[test]
+    fn it_denies_non_utf8_lines() {
+        assert!(
+            read_line(&mut &b"0006\xff\n"[..]).is_err(),
+            "The line was not utf-8, but didn't error"
+        );
+    }
+
+    #[rstest]
+    #[case(0, true)]
+    #[case(MAX_PAYLOAD, true)]
+    #[case(MAX_PAYLOAD + 1, false)]
+    fn it_bounds_written_packets(#[case] length: usize, #[case] allowed: bool) {
+        let packet = Packet::Data(vec![b'a'; length]);
+        let mut buffer = Vec::new();
+
+        assert_eq!(write_packet(&mut buffer, &packet).is_ok(), allowed);
+        if allowed && length > 0 {
+            assert_eq!(
+                read_packet(&mut buffer.as_slice()).expect("Unable to read the packet"),
+                packet
+            );
+        }
+    }
+
+    #[test]
+    fn it_roundtrips_lines() {
+        let lines = vec!["ok refs/heads/main".to_string(), String::new()];
+        let mut buffer = Vec::new();
+
+        write(&mut buffer, &lines).expect("Unable to write the lines");
+        assert_eq!(
+            read(&mut buffer.as_slice()).expect("Unable to read the lines"),
+            lines
+        );
+    }
+}
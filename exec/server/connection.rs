@@ -20,7 +20,7 @@ pub struct Connection<'f> {
     storage: &'f Path,
 
     addr: SocketAddr,
-    key: PublicKey,
+    key: Option<PublicKey>,
 }
 
 impl<'f> Connection<'f> {
@@ -29,7 +29,7 @@ impl<'f> Connection<'f> {
         gitconfig: &'f GitConfig,
         storage: &'f Path,
         addr: SocketAddr,
-        key: PublicKey,
+        key: Option<PublicKey>,
     ) -> Self {
         Self {
             session,
@@ -49,34 +49,30 @@ impl<'f> Connection<'f> {
             key,
         } = self;
 
+        // Anonymous clients are identified as such in the logs, in place of their key's fingerprint.
+        let user = key.as_ref().map_or_else(
+            || "anonymous".into(),
+            |key| key.fingerprint(Default::default()).to_string(),
+        );
+
         session
             .channel_opens()
             .err_into::<eyre::Error>()
             .try_for_each_concurrent(None, |request| {
-                let key = &key;
+                let (key, user) = (key.as_ref(), &user);
 
                 async move {
-                    tracing::info!(
-                        "Opening channel for `{}@{addr}`",
-                        key.fingerprint(Default::default())
-                    );
+                    tracing::info!("Opening channel for `{user}@{addr}`");
 
                     let channel = request.accept().await?;
                     let tunnel = Tunnel::new(storage, gitconfig, channel, key);
 
                     tunnel
                         .spin()
-                        .instrument(tracing::span!(
-                            tracing::Level::INFO,
-                            "tunnel",
-                            key = %key.fingerprint(Default::default())
-                        ))
+                        .instrument(tracing::span!(tracing::Level::INFO, "tunnel", key = %user))
                         .await?;
 
-                    tracing::info!(
-                        "Closing channel for `{}@{addr}`",
-                        key.fingerprint(Default::default())
-                    );
+                    tracing::info!("Closing channel for `{user}@{addr}`");
 
                     Ok(())
                 }
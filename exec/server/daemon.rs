@@ -0,0 +1,224 @@
+//! Types and structs related to _git daemon protocol_ handling.
+
+use std::{collections::HashMap, net::SocketAddr, path::PathBuf};
+
+use async_compat::CompatExt;
+use color_eyre::eyre;
+use futures::{io, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, TryFutureExt};
+use tokio::{
+    net::{TcpListener, TcpStream},
+    process::Command,
+};
+
+use furrow::Id;
+
+use super::{
+    process,
+    transport::{authorize, GitConfig, ServiceAccess},
+};
+
+/// A listener serving the repositories over the `git://` protocol, through `git-upload-pack`.
+///
+/// The requests are anonymous, and as such only allowed to read
+/// from the [`Visibility::Public`](furrow::entries::Visibility::Public)
+/// and [`Visibility::Archive`](furrow::entries::Visibility::Archive) repositories.
+#[derive(Debug)]
+pub struct Daemon {
+    storage: PathBuf,
+    gitconfig: GitConfig,
+}
+
+impl Daemon {
+    pub fn new(storage: PathBuf) -> Self {
+        let gitconfig = GitConfig::new(&storage);
+
+        Self { storage, gitconfig }
+    }
+
+    /// Accept and serve the connections from the `listener`, until it fails.
+    pub async fn serve(&'static self, listener: TcpListener) -> eyre::Result<()> {
+        loop {
+            let (stream, addr) = listener.accept().await?;
+
+            tokio::spawn(self.handle(stream, addr).inspect_err(move |err| {
+                tracing::error!("Daemon request from `{addr}` ended up in an error: {err}")
+            }));
+        }
+    }
+
+    /// Serve a single request from the `stream`.
+    async fn handle(&self, stream: TcpStream, addr: SocketAddr) -> eyre::Result<()> {
+        let (reader, writer) = stream.into_split();
+        let (mut reader, mut writer) = (reader.compat(), writer.compat());
+
+        let Request { command, protocol } = Request::read(&mut reader).await?;
+
+        tracing::info!("Received new daemon request from `{addr}`: {command}");
+
+        let Some(path) = command.strip_prefix("git-upload-pack ") else {
+            return Self::reject(&mut writer, "service not enabled").await;
+        };
+
+        // The path is not leaked to the client, whether it doesn't exist or has been denied.
+        let id = path.parse::<Id>().ok().filter(|id| {
+            matches!(
+                authorize(&self.storage, id, ServiceAccess::Read, None),
+                Ok((true, _))
+            ) && id.to_path(&self.storage).is_dir()
+        });
+        let Some(id) = id else {
+            return Self::reject(
+                &mut writer,
+                &format!("access denied or repository not exported: {path}"),
+            )
+            .await;
+        };
+
+        let mut envs = HashMap::new();
+        self.gitconfig.env(&mut envs);
+
+        if let Some(protocol) = protocol {
+            envs.insert("GIT_PROTOCOL".into(), protocol);
+        }
+
+        let child = process::spawn(
+            Command::new("git-upload-pack")
+                .env_clear()
+                .envs(envs)
+                .arg("--strict")
+                .arg("--timeout=3")
+                .arg(id.to_path(&self.storage)),
+        )?;
+
+        let status = process::pipe(
+            child,
+            |mut stdin| async move {
+                io::copy(&mut reader, &mut stdin).await?;
+                stdin.close().await
+            },
+            |stdout| async move {
+                io::copy(stdout, &mut writer).await?;
+                writer.close().await
+            },
+        )
+        .await?;
+
+        tracing::info!("Daemon request completed: {command}, {status}");
+
+        Ok(())
+    }
+
+    /// Reject the request with the `reason`, as an `ERR` pkt-line.
+    async fn reject(writer: &mut (impl AsyncWrite + Unpin), reason: &str) -> eyre::Result<()> {
+        tracing::warn!("Unable to process daemon request: {reason}");
+
+        writer
+            .write_all(format!("{:04x}ERR {reason}\n", reason.len() + 9).as_bytes())
+            .await?;
+        writer.flush().await?;
+
+        Ok(())
+    }
+}
+
+/// A request received from the client.
+#[derive(Debug, PartialEq)]
+struct Request {
+    /// The requested command, such as `git-upload-pack <path>`.
+    command: String,
+
+    /// The extra parameters of the request, joined as the `GIT_PROTOCOL` variable, if any.
+    protocol: Option<String>,
+}
+
+impl Request {
+    /// Read the request from the `reader`, formatted as
+    /// `git-upload-pack <path>\0host=<host>\0[\0<parameter>\0...]` in a pkt-line.
+    async fn read(reader: &mut (impl AsyncRead + Unpin)) -> eyre::Result<Self> {
+        let mut length = [0u8; 4];
+        reader.read_exact(&mut length).await?;
+        let length = std::str::from_utf8(&length)
+            .ok()
+            .filter(|length| length.bytes().all(|byte| byte.is_ascii_hexdigit()))
+            .and_then(|length| usize::from_str_radix(length, 16).ok())
+            .filter(|length| (5..=u16::MAX as usize).contains(length))
+            .ok_or_else(|| eyre::eyre!("Received a malformed request"))?;
+
+        let mut request = vec![0u8; length - 4];
+        reader.read_exact(&mut request).await?;
+        let request = String::from_utf8(request)?;
+
+        let (command, parameters) = request.split_once('\0').unwrap_or((&request, ""));
+
+        // The extra parameters follow the host after a second NUL byte, such as `version=2`.
+        let protocol = parameters
+            .split_once('\0')
+            .map(|(_, extra)| {
+                extra
+                    .split('\0')
+                    .filter(|parameter| !parameter.is_empty())
+                    .collect::<Vec<_>>()
+                    .join(":")
+            })
+            .filter(|protocol| !protocol.is_empty());
+
+        Ok(Self {
+            command: command.into(),
+            protocol,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+
+    #[rstest]
+    #[case(b"002fgit-upload-pack /repo.git\0host=example.com\0", None)]
+    #[case(b"001egit-upload-pack /repo.git\0", None)]
+    #[case(b"001dgit-upload-pack /repo.git", None)]
+    #[case(
+        b"003agit-upload-pack /repo.git\0host=example.com\0\0version=2\0",
+        Some("version=2")
+    )]
+    #[case(
+        b"004dgit-upload-pack /repo.git\0host=example.com\0\0version=2\0object-format=sha1\0",
+        Some("version=2:object-format=sha1")
+    )]
+    #[tokio::test]
+    async fn it_reads_requests(#[case] input: &[u8], #[case] protocol: Option<&str>) {
+        assert_eq!(
+            Request::read(&mut io::Cursor::new(input))
+                .await
+                .expect("Unable to read the request"),
+            Request {
+                command: "git-upload-pack /repo.git".into(),
+                protocol: protocol.map(Into::into),
+            }
+        );
+    }
+
+    #[rstest]
+    // Flush, delim and empty packets.
+    #[case(b"0000")]
+    #[case(b"0001")]
+    #[case(b"0004")]
+    // Malformed lengths.
+    #[case(b"zzzzgit-upload-pack /repo.git")]
+    #[case(b"+01dgit-upload-pack /repo.git")]
+    // Truncated inputs.
+    #[case(b"")]
+    #[case(b"00")]
+    #[case(b"0032git-upload-pack /repo.git")]
+    // Non utf-8 requests.
+    #[case(b"0008\xff\xfe\xfd\xfc")]
+    #[tokio::test]
+    async fn it_denies_malformed_requests(#[case] input: &[u8]) {
+        assert!(
+            Request::read(&mut io::Cursor::new(input)).await.is_err(),
+            "The request was malformed, but didn't error"
+        );
+    }
+}
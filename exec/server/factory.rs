@@ -1,4 +1,4 @@
-use std::{net::SocketAddr, path::PathBuf};
+use std::{net::SocketAddr, path::PathBuf, sync::Mutex};
 
 use assh_auth::handler;
 use color_eyre::eyre;
@@ -12,14 +12,21 @@ pub struct Factory {
     config: Server,
     gitconfig: GitConfig,
     storage: PathBuf,
+    anonymous: Option<String>,
 }
 
 impl Factory {
-    pub fn new(config: Server, gitconfig: GitConfig, storage: PathBuf) -> Self {
+    pub fn new(
+        config: Server,
+        gitconfig: GitConfig,
+        storage: PathBuf,
+        anonymous: Option<String>,
+    ) -> Self {
         Self {
             config,
             gitconfig,
             storage,
+            anonymous,
         }
     }
 
@@ -30,24 +37,43 @@ impl Factory {
     ) -> eyre::Result<Connection<'_>> {
         let session = assh::Session::new(stream, self.config.clone()).await?;
 
-        let (sender, receiver) = oneshot::channel::<handler::publickey::PublicKey>();
-        let sender = &mut Some(sender);
+        // The key is `None` for anonymous clients, authenticated with the `none` method.
+        let (sender, receiver) = oneshot::channel::<Option<handler::publickey::PublicKey>>();
+        let sender = &Mutex::new(Some(sender));
+        let send = move |key| {
+            sender
+                .lock()
+                .expect("Sender has been poisoned")
+                .take()
+                .expect("Sender has already been consumed at the time")
+                .send(key)
+                .ok();
+        };
+
         let session = session
             .handle(
-                handler::Auth::new(assh_connect::Service).publickey(|_, key| {
-                    sender
-                        .take()
-                        .expect("Sender has already been consumed at the time")
-                        .send(key)
-                        .ok();
-
-                    handler::publickey::Response::Accept
-                }),
+                handler::Auth::new(assh_connect::Service)
+                    // Only the designated username is accepted without authentication,
+                    // since clients attempt the `none` method before their keys.
+                    .none(|user| {
+                        if self.anonymous.as_ref() == Some(&user) {
+                            send(None);
+
+                            handler::none::Response::Accept
+                        } else {
+                            handler::none::Response::Reject
+                        }
+                    })
+                    .publickey(|_, key| {
+                        send(Some(key));
+
+                        handler::publickey::Response::Accept
+                    }),
             )
             .await?;
         let key = receiver
             .await
-            .expect("Unable to extract the key from the `publickey` authentication");
+            .expect("Unable to extract the key from the authentication");
 
         Ok(Connection::new(
             session,
@@ -0,0 +1,502 @@
+//! Types and structs related to _git smart HTTP_ handling.
+
+use std::{collections::HashMap, net::SocketAddr, path::PathBuf};
+
+use async_compat::CompatExt;
+use color_eyre::eyre;
+use futures::{
+    io::{self, BufReader},
+    AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt, TryFutureExt,
+};
+use tokio::{
+    net::{TcpListener, TcpStream},
+    process::Command,
+};
+
+use furrow::Id;
+
+use super::{
+    process,
+    transport::{authorize, GitConfig, ServiceAccess},
+};
+
+/// The maximum size of the request line and headers, as read from the client.
+const MAX_HEAD: u64 = 64 * 1024;
+
+/// The maximum size of a request body, as read from the client,
+/// matching the default buffer of `git http-backend`.
+const MAX_BODY: u64 = 10 * 1024 * 1024;
+
+/// The endpoints of the _smart_ protocol, relative to the repository.
+const ENDPOINTS: &[&str] = &["/info/refs", "/git-upload-pack", "/git-receive-pack"];
+
+/// A listener serving the repositories over git's _smart HTTP_ protocol, through `git http-backend`.
+///
+/// The requests are anonymous, and as such only allowed to read
+/// from the [`Visibility::Public`](furrow::entries::Visibility::Public)
+/// and [`Visibility::Archive`](furrow::entries::Visibility::Archive) repositories.
+#[derive(Debug)]
+pub struct Http {
+    storage: PathBuf,
+    gitconfig: GitConfig,
+}
+
+/// A request received from the client, with it's body.
+struct Request {
+    method: String,
+    path: String,
+    query: String,
+    headers: HashMap<String, String>,
+    body: Vec<u8>,
+}
+
+/// A failure to process a request, with the status and the message to respond with.
+struct Failure(&'static str, String);
+
+impl From<io::Error> for Failure {
+    fn from(err: io::Error) -> Self {
+        Self(
+            "400 Bad Request",
+            format!("Unable to read the request: {err}"),
+        )
+    }
+}
+
+impl Http {
+    pub fn new(storage: PathBuf) -> Self {
+        let gitconfig = GitConfig::new(&storage);
+
+        Self { storage, gitconfig }
+    }
+
+    /// Accept and serve the connections from the `listener`, until it fails.
+    pub async fn serve(&'static self, listener: TcpListener) -> eyre::Result<()> {
+        loop {
+            let (stream, addr) = listener.accept().await?;
+
+            tokio::spawn(self.handle(stream, addr).inspect_err(move |err| {
+                tracing::error!("HTTP request from `{addr}` ended up in an error: {err}")
+            }));
+        }
+    }
+
+    /// Serve a single request from the `stream`, closing the connection afterwards.
+    async fn handle(&self, stream: TcpStream, addr: SocketAddr) -> eyre::Result<()> {
+        let mut stream = BufReader::new(stream.compat());
+
+        let result = match Request::read(&mut stream).await {
+            Ok(Some(request)) => {
+                tracing::info!(
+                    "Received new HTTP request from `{addr}`: {} {}",
+                    request.method,
+                    request.path
+                );
+
+                self.exec(&request, addr, stream.get_mut()).await
+            }
+            Ok(None) => return Ok(()),
+            Err(err) => Err(err),
+        };
+
+        if let Err(Failure(status, message)) = result {
+            tracing::warn!("Unable to process HTTP request: {message}");
+
+            let writer = stream.get_mut();
+            writer
+                .write_all(
+                    format!(
+                        "HTTP/1.1 {status}\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{message}\n",
+                        message.len() + 1
+                    )
+                    .as_bytes(),
+                )
+                .await?;
+            writer.flush().await?;
+        }
+
+        Ok(())
+    }
+
+    /// Authorize the `request` and hand it to `git http-backend`, forwarding it's response to the `writer`.
+    async fn exec(
+        &self,
+        request: &Request,
+        addr: SocketAddr,
+        writer: &mut (impl AsyncWrite + Unpin),
+    ) -> Result<(), Failure> {
+        let not_found = || Failure("404 Not Found", format!("Not found: {}", request.path));
+
+        let (repository, endpoint) = ENDPOINTS
+            .iter()
+            .find_map(|endpoint| {
+                request
+                    .path
+                    .strip_suffix(endpoint)
+                    .map(|repository| (repository, *endpoint))
+            })
+            .ok_or_else(not_found)?;
+
+        // Only the _smart_ protocol is served, which announces the service in the query for the refs.
+        let service = match (request.method.as_str(), endpoint) {
+            ("GET", "/info/refs") => request
+                .query
+                .split('&')
+                .find_map(|param| param.strip_prefix("service="))
+                .filter(|service| matches!(*service, "git-upload-pack" | "git-receive-pack"))
+                .ok_or_else(|| {
+                    Failure(
+                        "403 Forbidden",
+                        "Only the smart HTTP protocol is supported".into(),
+                    )
+                })?,
+            ("POST", "/git-upload-pack") => "git-upload-pack",
+            ("POST", "/git-receive-pack") => "git-receive-pack",
+            _ => return Err(not_found()),
+        };
+        let access = match service {
+            "git-receive-pack" => ServiceAccess::Write,
+            _ => ServiceAccess::Read,
+        };
+
+        let id: Id = repository.parse().map_err(|_| not_found())?;
+        match authorize(&self.storage, &id, access, None) {
+            Ok((true, _)) if id.to_path(&self.storage).is_dir() => (),
+            Ok((true, _)) | Err(_) => return Err(not_found()),
+            Ok((false, _)) => {
+                return Err(Failure(
+                    "403 Forbidden",
+                    "The access to the repository has been denied".into(),
+                ))
+            }
+        }
+
+        let mut envs = HashMap::new();
+        self.gitconfig.env(&mut envs);
+
+        // Forward the request's encoding and the protocol version to the backend.
+        for (header, env) in [
+            ("content-type", "CONTENT_TYPE"),
+            ("content-encoding", "HTTP_CONTENT_ENCODING"),
+            ("git-protocol", "GIT_PROTOCOL"),
+        ] {
+            if let Some(value) = request.headers.get(header) {
+                envs.insert(env.into(), value.clone());
+            }
+        }
+
+        let child = process::spawn(
+            Command::new("git")
+                .arg("http-backend")
+                .env_clear()
+                .envs(envs)
+                .env("GIT_PROJECT_ROOT", &self.storage)
+                .env("GIT_HTTP_EXPORT_ALL", "1")
+                .env("REQUEST_METHOD", &request.method)
+                .env("PATH_INFO", format!("/{id}{endpoint}"))
+                .env("QUERY_STRING", &request.query)
+                .env("CONTENT_LENGTH", request.body.len().to_string())
+                .env("REMOTE_ADDR", addr.ip().to_string()),
+        )
+        .map_err(|err| {
+            Failure(
+                "500 Internal Server Error",
+                format!("Unable to spawn `git http-backend`: {err}"),
+            )
+        })?;
+
+        let result = process::pipe(
+            child,
+            |mut stdin| async move { stdin.write_all(&request.body).await },
+            |stdout| respond(BufReader::new(stdout), writer),
+        )
+        .await;
+
+        match result {
+            Ok(status) => tracing::info!(
+                "HTTP request completed: {} {}, {status}",
+                request.method,
+                request.path
+            ),
+            Err(err) => tracing::warn!("HTTP request transfer failed: {err}"),
+        }
+
+        Ok(())
+    }
+}
+
+impl Request {
+    /// Read the next request from the `reader`, or [`None`] if the client hung-up.
+    async fn read(reader: &mut (impl AsyncBufRead + Unpin)) -> Result<Option<Self>, Failure> {
+        let bad_request = |message: &str| Failure("400 Bad Request", message.into());
+
+        let mut head = reader.take(MAX_HEAD);
+        let mut line = String::new();
+        if head.read_line(&mut line).await? == 0 {
+            return Ok(None);
+        }
+
+        let mut parts = line.split_whitespace();
+        let (Some(method), Some(target), Some(_version), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(bad_request("Malformed request line"));
+        };
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        let (method, path, query) = (method.to_owned(), path.to_owned(), query.to_owned());
+
+        let mut headers = HashMap::new();
+        loop {
+            line.clear();
+            if head.read_line(&mut line).await? == 0 {
+                return Err(bad_request("Truncated request headers"));
+            }
+
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+
+            let (name, value) = line
+                .split_once(':')
+                .ok_or_else(|| bad_request("Malformed request header"))?;
+            headers.insert(name.trim().to_ascii_lowercase(), value.trim().to_owned());
+        }
+
+        let too_large = || {
+            Failure(
+                "413 Payload Too Large",
+                "The request body is too large".into(),
+            )
+        };
+
+        let mut body = Vec::new();
+        let mut reader = reader.take(MAX_BODY + 1);
+
+        // The body is cut short either by the client, or by the size limit.
+        let truncated = |reader: &io::Take<_>| match reader.limit() {
+            0 => too_large(),
+            _ => bad_request("Truncated request body"),
+        };
+        if headers
+            .get("transfer-encoding")
+            .is_some_and(|encoding| encoding.eq_ignore_ascii_case("chunked"))
+        {
+            loop {
+                line.clear();
+                reader.read_line(&mut line).await?;
+
+                let size = line.split(';').next().unwrap_or_default().trim();
+                let size = u64::from_str_radix(size, 16)
+                    .map_err(|_| bad_request("Malformed chunk size"))?;
+
+                if size == 0 {
+                    // Skip the trailers, up to the final empty line.
+                    while {
+                        line.clear();
+                        reader.read_line(&mut line).await? > 0 && !line.trim_end().is_empty()
+                    } {}
+
+                    break;
+                }
+
+                if (&mut reader).take(size).read_to_end(&mut body).await? as u64 != size {
+                    return Err(truncated(&reader));
+                }
+
+                line.clear();
+                reader.read_line(&mut line).await?;
+            }
+        } else if let Some(length) = headers.get("content-length") {
+            let length = length
+                .parse()
+                .map_err(|_| bad_request("Malformed content length"))?;
+            if length > MAX_BODY {
+                return Err(too_large());
+            }
+
+            if (&mut reader).take(length).read_to_end(&mut body).await? as u64 != length {
+                return Err(truncated(&reader));
+            }
+        }
+
+        if body.len() as u64 > MAX_BODY {
+            return Err(too_large());
+        }
+
+        Ok(Some(Self {
+            method,
+            path,
+            query,
+            headers,
+            body,
+        }))
+    }
+}
+
+/// Translate the CGI response of the `reader` to an HTTP response in the `writer`.
+async fn respond(
+    mut reader: impl AsyncBufRead + Unpin,
+    writer: &mut (impl AsyncWrite + Unpin),
+) -> io::Result<()> {
+    let mut status = "200 OK".to_owned();
+    let mut headers = String::new();
+
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line).await? == 0 {
+            break;
+        }
+
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+
+        match line.split_once(':') {
+            Some((name, value)) if name.eq_ignore_ascii_case("status") => {
+                status = value.trim().into()
+            }
+            _ => {
+                headers.push_str(line);
+                headers.push_str("\r\n");
+            }
+        }
+    }
+
+    writer
+        .write_all(format!("HTTP/1.1 {status}\r\n{headers}Connection: close\r\n\r\n").as_bytes())
+        .await?;
+    io::copy(reader, writer).await?;
+    writer.flush().await
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+
+    async fn read(input: &[u8]) -> Result<Option<Request>, Failure> {
+        Request::read(&mut io::Cursor::new(input)).await
+    }
+
+    #[rstest]
+    #[case(
+        "GET /repo.git/info/refs?service=git-upload-pack HTTP/1.1\r\nGit-Protocol: version=2\r\n\r\n",
+        "GET",
+        "/repo.git/info/refs",
+        "service=git-upload-pack",
+        ""
+    )]
+    #[case(
+        "POST /repo.git/git-upload-pack HTTP/1.1\r\nContent-Length: 4\r\n\r\n0000",
+        "POST",
+        "/repo.git/git-upload-pack",
+        "",
+        "0000"
+    )]
+    #[case(
+        "POST /repo.git/git-upload-pack HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n4;ext\r\n0014\r\n4\r\n0000\r\n0\r\nTrailer: value\r\n\r\n",
+        "POST",
+        "/repo.git/git-upload-pack",
+        "",
+        "00140000"
+    )]
+    #[tokio::test]
+    async fn it_reads_requests(
+        #[case] input: &str,
+        #[case] method: &str,
+        #[case] path: &str,
+        #[case] query: &str,
+        #[case] body: &str,
+    ) {
+        let Ok(Some(request)) = read(input.as_bytes()).await else {
+            panic!("Unable to read the request");
+        };
+
+        assert_eq!(request.method, method);
+        assert_eq!(request.path, path);
+        assert_eq!(request.query, query);
+        assert_eq!(request.body, body.as_bytes());
+    }
+
+    #[tokio::test]
+    async fn it_lowercases_headers() {
+        let Ok(Some(request)) = read(b"GET / HTTP/1.1\r\nGit-Protocol:version=2 \r\n\r\n").await
+        else {
+            panic!("Unable to read the request");
+        };
+
+        assert_eq!(
+            request.headers.get("git-protocol").map(String::as_str),
+            Some("version=2")
+        );
+    }
+
+    #[tokio::test]
+    async fn it_stops_on_hang_up() {
+        assert!(matches!(read(b"").await, Ok(None)));
+    }
+
+    #[rstest]
+    // Malformed request lines.
+    #[case(b"GET /\r\n\r\n", "400 Bad Request")]
+    #[case(b"GET / HTTP/1.1 extra\r\n\r\n", "400 Bad Request")]
+    #[case(b"\r\n\r\n", "400 Bad Request")]
+    // Malformed or truncated headers.
+    #[case(b"GET / HTTP/1.1\r\nHost", "400 Bad Request")]
+    #[case(b"GET / HTTP/1.1\r\nHost: example.com\r\n", "400 Bad Request")]
+    #[case(b"GET / HTTP/1.1\r\nmalformed\r\n\r\n", "400 Bad Request")]
+    // Malformed or truncated bodies.
+    #[case(b"POST / HTTP/1.1\r\nContent-Length: -1\r\n\r\n", "400 Bad Request")]
+    #[case(b"POST / HTTP/1.1\r\nContent-Length: 8\r\n\r\n0000", "400 Bad Request")]
+    #[case(
+        b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\nzz\r\n",
+        "400 Bad Request"
+    )]
+    #[case(
+        b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n8\r\n0000",
+        "400 Bad Request"
+    )]
+    #[case(
+        b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n4\r\n0000\r\n",
+        "400 Bad Request"
+    )]
+    #[case(
+        b"POST / HTTP/1.1\r\nContent-Length: 10485761\r\n\r\n",
+        "413 Payload Too Large"
+    )]
+    #[tokio::test]
+    async fn it_denies_malformed_requests(#[case] input: &[u8], #[case] expected: &str) {
+        let Err(Failure(status, _)) = read(input).await else {
+            panic!("The request was malformed, but didn't error");
+        };
+
+        assert_eq!(status, expected);
+    }
+
+    #[tokio::test]
+    async fn it_bounds_the_headers() {
+        let input = format!(
+            "GET / HTTP/1.1\r\nX-Padding: {}\r\n\r\n",
+            "a".repeat(MAX_HEAD as usize)
+        );
+
+        assert!(read(input.as_bytes()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn it_bounds_the_chunked_bodies() {
+        let input = format!(
+            "POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n{:x}\r\n{}\r\n0\r\n\r\n",
+            MAX_BODY + 1,
+            "a".repeat(MAX_BODY as usize + 1)
+        );
+
+        let Err(Failure(status, _)) = read(input.as_bytes()).await else {
+            panic!("The request was oversized, but didn't error");
+        };
+        assert_eq!(status, "413 Payload Too Large");
+    }
+}
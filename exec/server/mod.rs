@@ -1,6 +1,10 @@
 //! Types and structs related to _ssh connection & session handling_.
 
-use std::{net::SocketAddr, path::PathBuf, time::Duration};
+use std::{
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
 use assh::side::server;
 use async_compat::{Compat, CompatExt};
@@ -12,15 +16,27 @@ use futures::{
 };
 use tokio::net::TcpStream;
 
+use furrow::{authority, Id, Repository, AUTHORITY_REPOSITORY_NAME};
+
 mod connection;
 use connection::Connection;
 
+mod daemon;
+use daemon::Daemon;
+
 mod factory;
 use factory::Factory;
 
+mod http;
+use http::Http;
+
+mod process;
+
 mod transport;
 use transport::GitConfig;
 
+mod worker;
+
 /// An type alias for the socket used throughout the server implementation.
 pub type Socket = BufReader<BufWriter<Compat<TcpStream>>>;
 
@@ -32,10 +48,33 @@ pub struct Server {
     #[arg(short, long, required = true, num_args = 1)]
     pub bind: Vec<SocketAddr>,
 
+    /// The socket addresses to bind for the smart HTTP transport, can be supplied multiple times,
+    /// serving the public and archived repositories anonymously and read-only.
+    #[arg(long, num_args = 1)]
+    pub http_bind: Vec<SocketAddr>,
+
+    /// The socket addresses to bind for the `git://` daemon protocol, can be supplied multiple times,
+    /// serving the public and archived repositories anonymously and read-only.
+    #[arg(long, num_args = 1)]
+    pub git_daemon_bind: Vec<SocketAddr>,
+
     /// The keypairs to use, can be supplied multiple times.
     #[arg(short, long, num_args = 1)]
     pub keypair: Vec<PathBuf>,
 
+    /// The username allowed to connect without authentication,
+    /// with read-only access to the public and archived repositories.
+    #[arg(long)]
+    pub anonymous_user: Option<String>,
+
+    /// The program isolating the CI jobs, which are refused if it's unset.
+    ///
+    /// It's run as `<SANDBOX> <DIRECTORY> sh -c <COMMAND>`, and must run the command in the job's
+    /// work directory as an unprivileged user, without access to the storage directory,
+    /// such as a wrapper around `bwrap` or `nsjail`.
+    #[arg(long)]
+    pub jobs_sandbox: Option<PathBuf>,
+
     /// Banner text sent to the client on connections.
     #[arg(long)]
     pub banner: Option<String>,
@@ -80,6 +119,30 @@ impl Server {
             storage.display()
         );
 
+        // Migrate the authority repositories to the current versions of the entries' schemas,
+        // off the async workers since the migrations wait on the lock of the authorities
+        tokio::task::spawn_blocking({
+            let storage = storage.clone();
+
+            move || migrate(&storage)
+        })
+        .await??;
+
+        // Spawn the background workers processing the queued tasks
+        worker::spawn(worker::Webhooks, &storage);
+        worker::spawn(worker::Mirrors::new(storage.clone()), &storage);
+        worker::spawn(worker::Emails::new(storage.clone()), &storage);
+        worker::spawn(
+            worker::Jobs::new(storage.clone(), self.jobs_sandbox.clone()),
+            &storage,
+        );
+
+        // Spawn the background fetching of the repositories tracking an upstream
+        worker::Upstreams::new(storage.clone()).spawn();
+
+        let http = Http::new(storage.clone());
+        let daemon = Daemon::new(storage.clone());
+
         let factory = Box::leak(
             Factory::new(
                 server::Server {
@@ -98,10 +161,29 @@ impl Server {
                     gitconfig
                 },
                 storage,
+                self.anonymous_user,
             )
             .into(),
         );
 
+        if !self.http_bind.is_empty() {
+            let http = Box::leak(http.into());
+            let listener = tokio::net::TcpListener::bind(&*self.http_bind).await?;
+
+            tokio::spawn(http.serve(listener).inspect_err(|err: &eyre::Error| {
+                tracing::error!("The HTTP listener ended up in an error: {err}")
+            }));
+        }
+
+        if !self.git_daemon_bind.is_empty() {
+            let daemon = Box::leak(daemon.into());
+            let listener = tokio::net::TcpListener::bind(&*self.git_daemon_bind).await?;
+
+            tokio::spawn(daemon.serve(listener).inspect_err(|err: &eyre::Error| {
+                tracing::error!("The daemon listener ended up in an error: {err}")
+            }));
+        }
+
         let listener = tokio::net::TcpListener::bind(&*self.bind).await?;
         loop {
             let (stream, addr) = listener.accept().await?;
@@ -118,3 +200,30 @@ impl Server {
         }
     }
 }
+
+/// Migrate the authority repositories in the `storage` path to the current versions of the entries' schemas.
+fn migrate(storage: &Path) -> eyre::Result<()> {
+    for namespace in authority::namespaces(storage)? {
+        let id = Id::new(namespace, AUTHORITY_REPOSITORY_NAME);
+        let Ok(repository) = Repository::open(storage, &id) else {
+            continue;
+        };
+
+        let migrations = match id.namespace() {
+            None => authority::Global::migrate(&repository),
+            Some(_) => authority::Local::migrate(&repository),
+        };
+        match migrations {
+            Ok(migrations) => {
+                for migration in migrations {
+                    tracing::info!("Migrated {migration} in `{id}`");
+                }
+            }
+            Err(err) => {
+                tracing::warn!("Unable to migrate the authority repository `{id}`: {err}")
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,68 @@
+//! Types and structs related to _serving the clients through child processes_.
+
+use std::{
+    future::Future,
+    io,
+    process::{ExitStatus, Output, Stdio},
+};
+
+use async_compat::{Compat, CompatExt};
+use tokio::process::{Child, ChildStdin, ChildStdout, Command};
+
+/// Spawn the `command` with it's standard streams piped, to be served with [`pipe`].
+pub fn spawn(command: &mut Command) -> io::Result<Child> {
+    command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+}
+
+/// Serve the [`spawn`]ed `child` process until it exits, feeding it's `stdin` with the `input`
+/// and forwarding it's `stdout` with the `output`, while logging it's `stderr`.
+pub async fn pipe<I, O>(
+    mut child: Child,
+    input: impl FnOnce(Compat<ChildStdin>) -> I,
+    output: impl FnOnce(Compat<ChildStdout>) -> O,
+) -> io::Result<ExitStatus>
+where
+    I: Future<Output = io::Result<()>>,
+    O: Future<Output = io::Result<()>>,
+{
+    let (stdin, stdout) = (
+        child
+            .stdin
+            .take()
+            .expect("Unable to take the process' `stdin` handle")
+            .compat(),
+        child
+            .stdout
+            .take()
+            .expect("Unable to take the process' `stdout` handle")
+            .compat(),
+    );
+
+    let (status, (), ()) = tokio::try_join!(
+        // Wait for `child process` to exit.
+        async move {
+            let Output { status, stderr, .. } = child.wait_with_output().await?;
+
+            if !stderr.is_empty() {
+                tracing::warn!(
+                    "Process additional output (code {}): {}",
+                    status.code().unwrap_or(i32::MAX),
+                    String::from_utf8_lossy(&stderr)
+                );
+            }
+
+            Ok(status)
+        },
+        // Process `peer -> child process` communication.
+        input(stdin),
+        // Process `child process -> peer` communication.
+        output(stdout),
+    )?;
+
+    Ok(status)
+}
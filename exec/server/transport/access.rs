@@ -0,0 +1,119 @@
+use std::path::Path;
+
+use color_eyre::eyre;
+use ssh_key::PublicKey;
+
+use furrow::{
+    authority,
+    entries::{ArchiveConfig, Keychain, RegistrationPolicy, Role, Spec, Visibility},
+    id::Kind,
+    Id, Repository,
+};
+
+use super::service::ServiceAccess;
+
+/// Decide whether the `key` is allowed the `access` to the `target` repository,
+/// returning the decision along with the archive restrictions of the repository.
+///
+/// Anonymous requests, without a `key`, never create nor initialize any repository,
+/// and are only ever allowed to read the public and archived ones.
+pub fn authorize(
+    storage: &Path,
+    target: &Id,
+    access: ServiceAccess,
+    key: Option<&PublicKey>,
+) -> eyre::Result<(bool, ArchiveConfig)> {
+    // Open and load, or init, the global authority from the repository.
+    let authority = match key {
+        Some(key) => {
+            let global = Repository::open(storage, &Id::global_authority())
+                .or_else(|_| Repository::init(storage, &Id::global_authority()))?;
+
+            authority::Global::load_or_init(&global, key)?
+        }
+        None => authority::Global::load(&Repository::open(storage, &Id::global_authority())?)?,
+    };
+
+    // Automatically create the local authority repository if self-registration
+    // is allowed or the requester is from the global authority keychain.
+    if target.kind() == Kind::LocalAuthority
+        && key.is_some_and(|key| {
+            authority.global.registration == RegistrationPolicy::Allow
+                || authority.local.keychain.contains(key)
+        })
+    {
+        Repository::open(storage, target).or_else(|_| Repository::init(storage, target))?;
+    }
+
+    // Load or init the target authority from the repository.
+    let authority = match target.kind() {
+        Kind::GlobalAuthority => authority.local,
+        _ => {
+            let repository = Repository::open(storage, &target.to_authority())?;
+
+            match key {
+                Some(key) => authority::Local::load_or_init(&repository, key)?,
+                None => authority::Local::load(&repository)?,
+            }
+        }
+    };
+
+    if target.is_authority() {
+        return Ok((
+            key.is_some_and(|key| authority.keychain.contains(key)),
+            Default::default(),
+        ));
+    }
+
+    let repository = authority
+        .repositories
+        .get(target.repository())
+        .ok_or_else(|| eyre::eyre!("Missing repository definition for `{target}`"))?;
+
+    let allowed = allows(&authority.keychain, repository, access, key);
+
+    if allowed && key.is_some() {
+        // Create the target repository if non-existant.
+        Repository::open(storage, target).or_else(|_| Repository::init(storage, target))?;
+    }
+
+    Ok((allowed, repository.archive.clone()))
+}
+
+/// Load the authority of the `id` repository along with it's specification,
+/// failing alike for the missing repositories and the authority repositories.
+pub fn lookup(storage: &Path, id: &Id) -> eyre::Result<(authority::Local, Spec)> {
+    let not_found = || eyre::eyre!("The repository `{id}` does not exist");
+
+    let authority = Repository::open(storage, &id.to_authority()).map_err(|_| not_found())?;
+    let authority = authority::Local::load(&authority)?;
+    let spec = authority
+        .repositories
+        .get(id.repository())
+        .filter(|_| !id.is_authority())
+        .cloned()
+        .ok_or_else(not_found)?;
+
+    Ok((authority, spec))
+}
+
+/// Decide whether the `key` is allowed the `access` to the repository with the `spec`,
+/// from the `keychain` of it's namespace.
+pub fn allows(
+    keychain: &Keychain,
+    spec: &Spec,
+    access: ServiceAccess,
+    key: Option<&PublicKey>,
+) -> bool {
+    let has_role = |role| key.is_some_and(|key| keychain.has_role(role, key));
+
+    // Proposers are granted write access, and restricted to `refs/for/*` by the hooks.
+    let allowed = match spec.visibility {
+        Visibility::Private => has_role(Role::Propose),
+        Visibility::Public => access == ServiceAccess::Read || has_role(Role::Propose),
+        Visibility::Archive => access == ServiceAccess::Read,
+    };
+
+    // Repositories tracking an upstream are only ever written by the fetches.
+    allowed && (spec.upstream.is_none() || access == ServiceAccess::Read)
+}
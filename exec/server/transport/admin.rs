@@ -0,0 +1,374 @@
+use std::{path::Path, str::FromStr};
+
+use color_eyre::eyre;
+use ssh_key::{Fingerprint, PublicKey};
+
+use furrow::{
+    authority,
+    entries::{Entry, Keychain, RefConfig, Repositories, Spec, Transaction, Visibility},
+    id::Base,
+    Id, Repository, AUTHORITY_REPOSITORY_NAME,
+};
+
+/// The usage of the administration commands.
+const USAGE: &str = "\
+usage: repo create <repository> [--visibility <private|public|archive>] [--description <text>]
+       repo set <repository> <visibility|description|license> <value>
+       key add <namespace|/> <public key>
+       key rm <namespace|/> <fingerprint>
+       branch protect <repository> <branch> [--allow-force] [--allow-delete] [--require <context>]...";
+
+/// A command editing the entries of an authority repository, sent by the client as:
+///
+/// - `repo create <repository> [--visibility <visibility>] [--description <text>]`
+/// - `repo set <repository> <visibility|description|license> <value>`
+/// - `key add <namespace|/> <public key>`
+/// - `key rm <namespace|/> <fingerprint>`
+/// - `branch protect <repository> <branch> [--allow-force] [--allow-delete] [--require <context>]...`
+///
+/// The commands are only allowed to the keys of the namespace's keychain,
+/// and are committed to it's authority repository on their behalf.
+#[derive(Debug, Clone)]
+pub enum AdminCommand {
+    /// Define a new repository in the namespace.
+    RepoCreate {
+        repository: Id,
+        visibility: Visibility,
+        description: Option<String>,
+    },
+
+    /// Change a field of the repository's definition.
+    RepoSet { repository: Id, field: Field },
+
+    /// Add the `key` to the keychain of the namespace.
+    KeyAdd {
+        namespace: Option<Base>,
+        key: PublicKey,
+    },
+
+    /// Remove the key with the `fingerprint` from the keychain of the namespace.
+    KeyRm {
+        namespace: Option<Base>,
+        fingerprint: Fingerprint,
+    },
+
+    /// Protect the `branch` of the repository, with the `config`.
+    BranchProtect {
+        repository: Id,
+        branch: String,
+        config: RefConfig,
+    },
+}
+
+/// A field of the repository's definition, as changed by `repo set`.
+#[derive(Debug, Clone)]
+pub enum Field {
+    Visibility(Visibility),
+    Description(Option<String>),
+    License(Option<String>),
+}
+
+impl FromStr for AdminCommand {
+    type Err = eyre::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let usage = || eyre::eyre!(USAGE);
+
+        let args: Vec<_> = s
+            .split_whitespace()
+            .map(|arg| arg.trim_matches('\''))
+            .collect();
+        let namespace = |namespace: &str| match namespace {
+            "/" => Ok(None),
+            namespace => namespace.parse().map(Some),
+        };
+        let text = |words: &[&str]| Some(words.join(" ")).filter(|text| !text.is_empty());
+
+        match args[..] {
+            ["repo", "create", repository, ref flags @ ..] => {
+                let mut visibility = Visibility::default();
+                let mut description = None;
+
+                let mut flags = flags.iter();
+                while let Some(flag) = flags.next() {
+                    match *flag {
+                        "--visibility" => {
+                            visibility = flags
+                                .next()
+                                .ok_or_else(usage)?
+                                .parse()
+                                .map_err(|_| usage())?
+                        }
+                        // The description spans the remaining arguments.
+                        "--description" => {
+                            description = text(flags.as_slice());
+                            break;
+                        }
+                        _ => return Err(usage()),
+                    }
+                }
+
+                Ok(Self::RepoCreate {
+                    repository: repository.parse()?,
+                    visibility,
+                    description,
+                })
+            }
+            ["repo", "set", repository, field, ref value @ ..] => {
+                let field = match field {
+                    "visibility" => Field::Visibility(
+                        text(value)
+                            .ok_or_else(usage)?
+                            .parse()
+                            .map_err(|_| usage())?,
+                    ),
+                    "description" => Field::Description(text(value)),
+                    "license" => Field::License(text(value)),
+                    _ => return Err(usage()),
+                };
+
+                Ok(Self::RepoSet {
+                    repository: repository.parse()?,
+                    field,
+                })
+            }
+            ["key", "add", ns, ref key @ ..] if !key.is_empty() => Ok(Self::KeyAdd {
+                namespace: namespace(ns)?,
+                key: key.join(" ").parse()?,
+            }),
+            ["key", "rm", ns, fingerprint] => Ok(Self::KeyRm {
+                namespace: namespace(ns)?,
+                fingerprint: fingerprint.parse()?,
+            }),
+            ["branch", "protect", repository, branch, ref flags @ ..] => {
+                let mut config = RefConfig::protected();
+
+                let mut flags = flags.iter();
+                while let Some(flag) = flags.next() {
+                    match *flag {
+                        "--allow-force" => config.allow_force = true,
+                        "--allow-delete" => config.allow_delete = true,
+                        "--require" => config
+                            .require_statuses
+                            .push(flags.next().ok_or_else(usage)?.to_string()),
+                        _ => return Err(usage()),
+                    }
+                }
+
+                Ok(Self::BranchProtect {
+                    repository: repository.parse()?,
+                    branch: branch.into(),
+                    config,
+                })
+            }
+            _ => Err(usage()),
+        }
+    }
+}
+
+impl AdminCommand {
+    /// Execute the command on behalf of the `key`, returning the output for the client.
+    pub fn exec(&self, storage: &Path, key: &PublicKey) -> eyre::Result<String> {
+        let namespace = match self {
+            Self::RepoCreate { repository, .. }
+            | Self::RepoSet { repository, .. }
+            | Self::BranchProtect { repository, .. } => {
+                if repository.is_authority() {
+                    eyre::bail!("The authority repository `{repository}` can't be administrated with commands");
+                }
+
+                repository.namespace().cloned()
+            }
+            Self::KeyAdd { namespace, .. } | Self::KeyRm { namespace, .. } => namespace.clone(),
+        };
+        let name = namespace
+            .as_ref()
+            .map_or_else(|| "the global namespace".into(), |ns| format!("`{ns}`"));
+
+        let authority = Repository::open(
+            storage,
+            &Id::new(namespace.clone(), AUTHORITY_REPOSITORY_NAME),
+        )
+        .map_err(|_| eyre::eyre!("The namespace {name} does not exist"))?;
+        let denied = || eyre::eyre!("The administration of {name} has been denied");
+
+        // Authorize the key before locking, not to let any key hold up the other writers.
+        if !Keychain::load(&authority)?.contains(key) {
+            return Err(denied());
+        }
+
+        let transaction = Transaction::begin(&authority)?;
+        let mut keychain: Keychain = transaction.load()?;
+        let mut repositories: Repositories = transaction.load()?;
+
+        // The keychain may have changed while waiting for the lock.
+        if !keychain.contains(key) {
+            return Err(denied());
+        }
+
+        // Record the client as the author of the modification,
+        // falling back to it's fingerprint in the absence of an email address.
+        let fingerprint = key.fingerprint(Default::default()).to_string();
+        let author =
+            git2::Signature::now(&fingerprint, keychain.email(key).unwrap_or(&fingerprint))?;
+
+        match self {
+            Self::RepoCreate {
+                repository: id,
+                visibility,
+                description,
+            } => {
+                if repositories.contains_key(id.repository()) {
+                    eyre::bail!("The repository `{id}` already exists");
+                }
+
+                repositories.insert(
+                    (**id.repository()).clone(),
+                    Spec {
+                        visibility: visibility.clone(),
+                        description: description.clone(),
+                        ..Default::default()
+                    },
+                );
+
+                save(
+                    transaction,
+                    namespace.as_ref(),
+                    &repositories,
+                    &format!("Create the `{id}` repository"),
+                    &author,
+                )?;
+
+                Ok(format!("Created the `{id}` repository\n"))
+            }
+            Self::RepoSet {
+                repository: id,
+                field,
+            } => {
+                let spec = spec_mut(&mut repositories, id)?;
+
+                let name = match field {
+                    Field::Visibility(visibility) => {
+                        spec.visibility = visibility.clone();
+                        "visibility"
+                    }
+                    Field::Description(description) => {
+                        spec.description = description.clone();
+                        "description"
+                    }
+                    Field::License(license) => {
+                        spec.license = license.clone();
+                        "license"
+                    }
+                };
+
+                save(
+                    transaction,
+                    namespace.as_ref(),
+                    &repositories,
+                    &format!("Set the {name} of the `{id}` repository"),
+                    &author,
+                )?;
+
+                Ok(format!("Updated the {name} of the `{id}` repository\n"))
+            }
+            Self::KeyAdd { key: added, .. } => {
+                let fingerprint = added.fingerprint(Default::default());
+
+                if !keychain.add(added.clone()) {
+                    eyre::bail!("The key `{fingerprint}` is already in the keychain of {name}");
+                }
+
+                save(
+                    transaction,
+                    namespace.as_ref(),
+                    &keychain,
+                    &format!("Add the `{fingerprint}` key to the keychain"),
+                    &author,
+                )?;
+
+                Ok(format!(
+                    "Added the key `{fingerprint}` to the keychain of {name}\n"
+                ))
+            }
+            Self::KeyRm { fingerprint, .. } => {
+                if !keychain.remove(fingerprint) {
+                    eyre::bail!("The key `{fingerprint}` is not in the keychain of {name}, or is it's last one");
+                }
+
+                save(
+                    transaction,
+                    namespace.as_ref(),
+                    &keychain,
+                    &format!("Remove the `{fingerprint}` key from the keychain"),
+                    &author,
+                )?;
+
+                Ok(format!(
+                    "Removed the key `{fingerprint}` from the keychain of {name}\n"
+                ))
+            }
+            Self::BranchProtect {
+                repository: id,
+                branch,
+                config,
+            } => {
+                let spec = spec_mut(&mut repositories, id)?;
+
+                if !git2::Reference::is_valid_name(&format!("refs/heads/{branch}")) {
+                    eyre::bail!("The branch name `{branch}` is invalid");
+                }
+
+                // Keep the freeze windows of the branch, which are not configurable from here.
+                let freeze = spec
+                    .branch
+                    .get(branch)
+                    .map(|config| config.freeze.clone())
+                    .unwrap_or_default();
+                spec.branch.insert(
+                    branch.clone(),
+                    RefConfig {
+                        freeze,
+                        ..config.clone()
+                    },
+                );
+
+                save(
+                    transaction,
+                    namespace.as_ref(),
+                    &repositories,
+                    &format!("Protect the `{branch}` branch of the `{id}` repository"),
+                    &author,
+                )?;
+
+                Ok(format!(
+                    "Protected the `{branch}` branch of the `{id}` repository\n"
+                ))
+            }
+        }
+    }
+}
+
+/// Get the mutable specification of the `id` repository from the `repositories`.
+fn spec_mut<'r>(repositories: &'r mut Repositories, id: &Id) -> eyre::Result<&'r mut Spec> {
+    repositories
+        .get_mut(id.repository())
+        .ok_or_else(|| eyre::eyre!("The repository `{id}` does not exist"))
+}
+
+/// Commit the `entry` in the `transaction`, after verifying the entries
+/// of the authority repository in the `namespace`, as the `pre-receive` hook would for a push.
+fn save<A, T: Entry<A>>(
+    mut transaction: Transaction<'_>,
+    namespace: Option<&Base>,
+    entry: &T,
+    message: &str,
+    author: &git2::Signature,
+) -> eyre::Result<()> {
+    transaction.stage(entry)?;
+    authority::verify(namespace, |path| transaction.read(path), None, None)?;
+
+    transaction.commit(message, author)?;
+
+    Ok(())
+}
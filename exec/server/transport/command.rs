@@ -0,0 +1,274 @@
+use std::{fmt::Write, path::Path};
+
+use color_eyre::eyre;
+use ssh_key::PublicKey;
+
+use furrow::{authority, entries::Role, id::Base, Id, Repository, AUTHORITY_REPOSITORY_NAME};
+
+use super::{
+    access::{allows, lookup},
+    service::ServiceAccess,
+    AdminCommand, ProposalsCommand, StatusCommand, SubmitCommand,
+};
+
+/// The usage of the commands, as sent by `help`.
+const HELP: &str = "\
+usage: ssh <host> <command> [args...]
+
+  whoami                         show who you are authenticated as
+  ls                             list the repositories you have access to
+  info <repository>              show the configuration of a repository
+  status <get|set> ...           read or report commit statuses
+  proposals <list|merge|close> ...
+                                 list, merge or close proposals
+  submit <repository> [branch] < series.mbox
+                                 submit a patch series as a proposal
+  repo <create|set> ...          define or change a repository of a namespace
+  key <add|rm> ...               add or remove a key of a namespace's keychain
+  branch protect ...             protect a branch of a repository
+  help                           show this message
+
+The repositories are cloned with `git clone <host>:<repository>`.
+";
+
+/// A command sent by the client in place of a git service, as `ssh <host> <command>`.
+#[derive(Debug)]
+pub enum Command {
+    /// Show who the client is authenticated as, and it's memberships.
+    Whoami,
+
+    /// List the repositories the client is allowed to read.
+    Ls,
+
+    /// Show the configuration of the repository.
+    Info { repository: Id },
+
+    /// Show the usage of the commands.
+    Help,
+
+    /// Read or report commit statuses.
+    Status(StatusCommand),
+
+    /// List or act on the proposals of a repository.
+    Proposals(ProposalsCommand),
+
+    /// Submit a patch series as a proposal.
+    Submit(SubmitCommand),
+
+    /// Edit the entries of an authority repository.
+    Admin(AdminCommand),
+}
+
+impl Command {
+    /// Parse the `command`, or return [`None`] if it's not one of ours, such as a git service.
+    pub fn parse(command: &str) -> Option<eyre::Result<Self>> {
+        let (name, args) = command.split_once(' ').unwrap_or((command, ""));
+        let bare = |command| match args.trim() {
+            "" => Ok(command),
+            _ => Err(eyre::eyre!("usage: {name}")),
+        };
+
+        Some(match name {
+            "whoami" => bare(Self::Whoami),
+            "ls" => bare(Self::Ls),
+            "help" => bare(Self::Help),
+            "info" => match args.split_whitespace().collect::<Vec<_>>()[..] {
+                [repository] => repository
+                    .trim_matches('\'')
+                    .parse()
+                    .map(|repository| Self::Info { repository })
+                    .map_err(Into::into),
+                _ => Err(eyre::eyre!("usage: info <repository>")),
+            },
+            "status" => args.parse().map(Self::Status),
+            "proposals" => args.parse().map(Self::Proposals),
+            "submit" => args.parse().map(Self::Submit),
+            "repo" | "key" | "branch" => command.parse().map(Self::Admin),
+            _ => return None,
+        })
+    }
+
+    /// Whether the command reads the client's input, until it's end.
+    pub fn reads_input(&self) -> bool {
+        matches!(self, Self::Submit(_))
+    }
+
+    /// Execute the command on behalf of the `key`, or anonymously,
+    /// with the client's `input`, returning the output for the client.
+    pub async fn exec(
+        &self,
+        storage: &Path,
+        key: Option<&PublicKey>,
+        input: &[u8],
+    ) -> eyre::Result<String> {
+        match self {
+            Self::Whoami => whoami(storage, key),
+            Self::Ls => ls(storage, key),
+            Self::Info { repository } => info(storage, key, repository),
+            Self::Help => Ok(HELP.into()),
+            Self::Status(command) => command.exec(storage, authenticated(key)?),
+            Self::Proposals(command) => command.exec(storage, authenticated(key)?).await,
+            Self::Submit(command) => command.exec(storage, authenticated(key)?, input).await,
+            // The commands wait on the lock of the authority, so run them off the async workers.
+            Self::Admin(command) => {
+                let (command, storage, key) = (
+                    command.clone(),
+                    storage.to_owned(),
+                    authenticated(key)?.clone(),
+                );
+
+                tokio::task::spawn_blocking(move || command.exec(&storage, &key)).await?
+            }
+        }
+    }
+}
+
+/// The message sent to the clients requesting a shell, which is not provided.
+pub fn greeting(key: Option<&PublicKey>) -> String {
+    let user = key.map_or_else(
+        || "anonymous".into(),
+        |key| key.fingerprint(Default::default()).to_string(),
+    );
+
+    format!("Hi `{user}`, there is no shell access here.\nRun `ssh <host> help` to list the available commands.\n")
+}
+
+/// Get the `key` of the client, the commands acting on the repositories being denied to anonymous clients.
+fn authenticated(key: Option<&PublicKey>) -> eyre::Result<&PublicKey> {
+    key.ok_or_else(|| {
+        eyre::eyre!("This command is only available to clients authenticated with a key")
+    })
+}
+
+/// Load the authorities of all the namespaces of the `storage`,
+/// skipping the ones which have not been initialized yet.
+fn authorities(storage: &Path) -> eyre::Result<Vec<(Option<Base>, authority::Local)>> {
+    Ok(authority::namespaces(storage)?
+        .into_iter()
+        .filter_map(|namespace| {
+            let id = Id::new(namespace.clone(), AUTHORITY_REPOSITORY_NAME);
+            let authority = authority::Local::load(&Repository::open(storage, &id).ok()?).ok()?;
+
+            Some((namespace, authority))
+        })
+        .collect())
+}
+
+fn whoami(storage: &Path, key: Option<&PublicKey>) -> eyre::Result<String> {
+    let Some(key) = key else {
+        return Ok(
+            "You are connected anonymously, with read-only access to the public repositories.\n"
+                .into(),
+        );
+    };
+
+    let mut output = format!(
+        "You are authenticated as `{}` ({})\n",
+        key.fingerprint(Default::default()),
+        key.algorithm()
+    );
+
+    for (namespace, authority) in authorities(storage)? {
+        let namespace = match &namespace {
+            Some(namespace) => format!("the `{namespace}` namespace"),
+            None => "the global namespace".into(),
+        };
+
+        if authority.keychain.contains(key) {
+            writeln!(output, "- member of the keychain of {namespace}")?;
+
+            continue;
+        }
+
+        for role in [Role::Status, Role::Propose] {
+            if authority.keychain.has_role(role, key) {
+                writeln!(output, "- `{role}` role in {namespace}")?;
+            }
+        }
+    }
+
+    Ok(output)
+}
+
+fn ls(storage: &Path, key: Option<&PublicKey>) -> eyre::Result<String> {
+    let authorities = authorities(storage)?;
+
+    let mut repositories = Vec::new();
+    for (namespace, authority) in &authorities {
+        for (name, spec) in authority.repositories.iter() {
+            if allows(&authority.keychain, spec, ServiceAccess::Read, key) {
+                repositories.push((Id::new(namespace.clone(), name.clone()), spec));
+            }
+        }
+    }
+    repositories.sort_by_cached_key(|(id, _)| id.to_string());
+
+    Ok(repositories
+        .iter()
+        .map(|(id, spec)| {
+            format!(
+                "{:<32} {:<8} {}\n",
+                id.to_string(),
+                spec.visibility.to_string(),
+                spec.description.as_deref().unwrap_or("-")
+            )
+        })
+        .collect())
+}
+
+fn info(storage: &Path, key: Option<&PublicKey>, id: &Id) -> eyre::Result<String> {
+    let (authority, spec) = lookup(storage, id)?;
+    if !allows(&authority.keychain, &spec, ServiceAccess::Read, key) {
+        eyre::bail!("The repository `{id}` does not exist");
+    }
+
+    let access = if allows(&authority.keychain, &spec, ServiceAccess::Write, key) {
+        "read-write"
+    } else {
+        "read-only"
+    };
+
+    let mut output = String::new();
+    writeln!(output, "repository:  {id}")?;
+    writeln!(
+        output,
+        "description: {}",
+        spec.description.as_deref().unwrap_or("-")
+    )?;
+    writeln!(
+        output,
+        "license:     {}",
+        spec.license.as_deref().unwrap_or("-")
+    )?;
+    writeln!(output, "visibility:  {}", spec.visibility)?;
+    writeln!(output, "access:      {access}")?;
+    if let Some(upstream) = &spec.upstream {
+        writeln!(output, "upstream:    {}", upstream.url)?;
+    }
+    if let Some(branches) = &spec.branches {
+        writeln!(output, "branches:    matching `{branches}`")?;
+    }
+
+    let mut branches: Vec<_> = spec.branch.iter().collect();
+    branches.sort_by_key(|(name, _)| *name);
+    for (name, config) in branches {
+        let allowed = |allowed| if allowed { "allowed" } else { "denied" };
+
+        write!(
+            output,
+            "branch `{name}`: force-pushes {}, deletions {}",
+            allowed(config.allow_force),
+            allowed(config.allow_delete)
+        )?;
+        if !config.require_statuses.is_empty() {
+            write!(
+                output,
+                ", requires `{}`",
+                config.require_statuses.join("`, `")
+            )?;
+        }
+        writeln!(output)?;
+    }
+
+    Ok(output)
+}
@@ -35,6 +35,12 @@ impl GitConfig {
     /// - `receive.denyDeleteCurrent`: `false`
     ///   Allows the client to delete the `HEAD` branch.
     ///
+    /// - `receive.advertisePushOptions`: `true`
+    ///   Allows the client to send push options to the hooks, with `git push -o <option>`.
+    ///
+    /// - `receive.procReceiveRefs`: `refs/for`
+    ///   Delegates the pushes to `refs/for/<branch>` to the `proc-receive` hook, submitting proposals.
+    ///
     pub fn populate(&self) -> Result<(), git2::Error> {
         let mut config = git2::Config::open(&self.path)?;
 
@@ -49,6 +55,8 @@ impl GitConfig {
         config.set_i32("receive.keepAlive", 3)?;
         config.set_bool("receive.fsckObjects", true)?;
         config.set_str("receive.denyDeleteCurrent", "ignore")?;
+        config.set_bool("receive.advertisePushOptions", true)?;
+        config.set_str("receive.procReceiveRefs", "refs/for")?;
 
         Ok(())
     }
@@ -1,7 +1,25 @@
 //! Types and structs related to _git packs and tunnel handling_.
 
+mod access;
+pub use access::authorize;
+
 mod service;
-pub use service::Service;
+pub use service::{Service, ServiceAccess};
+
+mod command;
+pub use command::Command;
+
+mod admin;
+pub use admin::AdminCommand;
+
+mod proposals;
+pub use proposals::ProposalsCommand;
+
+mod status;
+pub use status::StatusCommand;
+
+mod submit;
+pub use submit::SubmitCommand;
 
 mod tunnel;
 pub use tunnel::Tunnel;
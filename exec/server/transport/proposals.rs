@@ -0,0 +1,199 @@
+use std::{collections::HashMap, path::Path, str::FromStr};
+
+use color_eyre::eyre;
+use ssh_key::PublicKey;
+use tokio::process::Command;
+
+use furrow::{
+    authority,
+    entries::{Role, Visibility},
+    proposal::{Proposal, State},
+    Id, Repository,
+};
+
+use super::access::lookup;
+use crate::hooks::Hooks;
+
+/// A command listing or acting on the proposals of a repository, sent by the client as:
+///
+/// - `proposals list <repository>`
+/// - `proposals merge <repository> <number>`
+/// - `proposals close <repository> <number>`
+#[derive(Debug)]
+pub enum ProposalsCommand {
+    /// List the proposals of the repository.
+    List { repository: Id },
+
+    /// Fast-forward the target branch of the proposal to it's head.
+    Merge { repository: Id, number: u64 },
+
+    /// Close the proposal without merging it.
+    Close { repository: Id, number: u64 },
+}
+
+impl FromStr for ProposalsCommand {
+    type Err = eyre::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let usage = || {
+            eyre::eyre!(
+                "usage: proposals list <repository>\n       proposals merge <repository> <number>\n       proposals close <repository> <number>"
+            )
+        };
+
+        let mut args = s.split_whitespace();
+        let action = args.next().ok_or_else(usage)?;
+        let repository = args.next().ok_or_else(usage)?.trim_matches('\'').parse()?;
+        let number = args
+            .next()
+            .map(|number| number.trim_start_matches('#').parse().map_err(|_| usage()))
+            .transpose()?;
+
+        if args.next().is_some() {
+            return Err(usage());
+        }
+
+        match (action, number) {
+            ("list", None) => Ok(Self::List { repository }),
+            ("merge", Some(number)) => Ok(Self::Merge { repository, number }),
+            ("close", Some(number)) => Ok(Self::Close { repository, number }),
+            _ => Err(usage()),
+        }
+    }
+}
+
+impl ProposalsCommand {
+    /// Execute the command on behalf of the `key`, returning the output for the client.
+    pub async fn exec(&self, storage: &Path, key: &PublicKey) -> eyre::Result<String> {
+        let (Self::List { repository: id }
+        | Self::Merge { repository: id, .. }
+        | Self::Close { repository: id, .. }) = self;
+
+        let (authority::Local { keychain, .. }, spec) = lookup(storage, id)?;
+
+        if !(keychain.has_role(Role::Propose, key)
+            || matches!(spec.visibility, Visibility::Public | Visibility::Archive))
+        {
+            eyre::bail!("The access to the proposals of `{id}` has been denied");
+        }
+
+        let repository = Repository::open(storage, id)?;
+
+        let number = match self {
+            Self::List { .. } => {
+                return Ok(Proposal::list(&repository)?
+                    .iter()
+                    .map(|proposal| {
+                        format!(
+                            "#{:<4} {:<6} {}\t{}\t{}\n",
+                            proposal.number,
+                            proposal.state.to_string(),
+                            proposal.target,
+                            proposal
+                                .topic
+                                .as_deref()
+                                .or(proposal
+                                    .series
+                                    .as_ref()
+                                    .map(|series| series.subject.as_str()))
+                                .unwrap_or("-"),
+                            proposal.author,
+                        )
+                    })
+                    .collect())
+            }
+            Self::Merge { number, .. } | Self::Close { number, .. } => *number,
+        };
+
+        let mut proposal = Proposal::load(&repository, number)?
+            .ok_or_else(|| eyre::eyre!("The proposal #{number} does not exist in `{id}`"))?;
+        if proposal.state != State::Open {
+            eyre::bail!("The proposal #{number} is already {}", proposal.state);
+        }
+
+        let author = proposal.author == key.fingerprint(Default::default()).to_string();
+        let allowed = match self {
+            Self::Merge { .. } => keychain.contains(key),
+            _ => keychain.contains(key) || author,
+        };
+        if !allowed {
+            eyre::bail!("The access to the proposal #{number} of `{id}` has been denied");
+        }
+
+        if let Self::Merge { .. } = self {
+            Self::merge(storage, id, key, &repository, &proposal).await?;
+        }
+
+        proposal.state = match self {
+            Self::Merge { .. } => State::Merged,
+            _ => State::Closed,
+        };
+        proposal.save(&repository)?;
+
+        Ok(format!(
+            "The proposal #{number} to `{}` is now {}\n",
+            proposal.target, proposal.state
+        ))
+    }
+
+    /// Fast-forward the target branch of the `proposal` to it's head,
+    /// by pushing it locally for the hooks to enforce the branch's protections.
+    async fn merge(
+        storage: &Path,
+        id: &Id,
+        key: &PublicKey,
+        repository: &Repository,
+        proposal: &Proposal,
+    ) -> eyre::Result<()> {
+        let target = format!("refs/heads/{}", proposal.target);
+        let head = repository
+            .find_reference(&proposal.refname())?
+            .peel_to_commit()?
+            .id();
+        let base = repository
+            .find_reference(&target)
+            .map_err(|_| eyre::eyre!("The target branch `{}` does not exist", proposal.target))?
+            .peel_to_commit()?
+            .id();
+
+        // The changes are already part of the target branch.
+        if head == base || repository.graph_descendant_of(base, head)? {
+            return Ok(());
+        }
+
+        if !repository.graph_descendant_of(head, base)? {
+            eyre::bail!(
+                "The proposal #{} cannot be fast-forwarded onto `{}`, rebase it and push it again",
+                proposal.number,
+                proposal.target
+            );
+        }
+
+        let mut envs = HashMap::new();
+        Hooks::install(storage, id)?;
+        Hooks::env(&mut envs, storage, id, Some(key))?;
+
+        let path = id.to_path(storage);
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(&path)
+            .args(["push", "--quiet"])
+            .arg(format!("--force-with-lease={target}:{base}"))
+            .arg(&path)
+            .arg(format!("{head}:{target}"))
+            .envs(envs)
+            .kill_on_drop(true)
+            .output()
+            .await?;
+
+        if !output.status.success() {
+            eyre::bail!(
+                "Unable to merge the proposal #{}:\n{}",
+                proposal.number,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+
+        Ok(())
+    }
+}
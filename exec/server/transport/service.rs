@@ -1,18 +1,18 @@
-use std::{
-    ffi::OsStr,
-    path::Path,
-    process::{ExitStatus, Output, Stdio},
-};
+use std::{ffi::OsStr, os::unix::process::ExitStatusExt, path::Path, process::ExitStatus};
 
 use assh::{side::Side, Pipe};
 use assh_connect::channel::{request::Request, Channel};
-use async_compat::CompatExt;
 use color_eyre::eyre;
-use futures::{AsyncReadExt, AsyncWriteExt};
+use futures::{AsyncRead, AsyncReadExt, AsyncWriteExt};
 // TODO: Remove parse_display to enable bubbling up the parse errors.
 use parse_display::{Display, FromStr};
 use tokio::process::Command;
 
+use furrow::entries::ArchiveConfig;
+
+use crate::lfs;
+use crate::server::process;
+
 /// A definition of what access the services requires to perform it's action.
 #[derive(Debug, PartialEq)]
 pub enum ServiceAccess {
@@ -30,6 +30,16 @@ pub enum Service {
 
     /// Invoked by `git send-pack` and updates the repository with the information fed from the remote end.
     GitReceivePack { repository: furrow::Id },
+
+    /// Invoked by `git archive --remote`, sends an archive of the requested tree-ish.
+    GitUploadArchive { repository: furrow::Id },
+
+    /// Invoked by `git-lfs` over SSH, transfers the LFS objects and manages the file locks.
+    #[display("git-lfs-transfer '{repository}' {operation}")]
+    GitLfsTransfer {
+        repository: furrow::Id,
+        operation: lfs::Operation,
+    },
 }
 
 impl Service {
@@ -37,6 +47,8 @@ impl Service {
         match self {
             Service::GitUploadPack { repository } => repository,
             Service::GitReceivePack { repository } => repository,
+            Service::GitUploadArchive { repository } => repository,
+            Service::GitLfsTransfer { repository, .. } => repository,
         }
     }
 
@@ -44,88 +56,100 @@ impl Service {
         match self {
             Service::GitUploadPack { .. } => ServiceAccess::Read,
             Service::GitReceivePack { .. } => ServiceAccess::Write,
+            Service::GitUploadArchive { .. } => ServiceAccess::Read,
+            Service::GitLfsTransfer { operation, .. } => match operation {
+                lfs::Operation::Upload => ServiceAccess::Write,
+                lfs::Operation::Download => ServiceAccess::Read,
+            },
         }
     }
 
+    /// Execute the service, piping it's I/O to the `channel`,
+    /// with the `archive` restrictions applying to [`Service::GitUploadArchive`].
     pub async fn exec(
         &self,
         envs: impl IntoIterator<Item = (impl AsRef<OsStr>, impl AsRef<OsStr>)>,
         storage: &Path,
+        archive: &ArchiveConfig,
         channel: &Channel<'_, impl Pipe, impl Side>,
         request: Request<'_, impl Pipe, impl Side>,
     ) -> eyre::Result<ExitStatus> {
-        let mut child = match self {
-            Self::GitUploadPack { repository } => Command::new("git-upload-pack")
-                .env_clear()
-                .envs(envs)
-                .arg("--strict")
-                .arg("--timeout=3")
-                .arg(repository.to_path(storage))
-                .stdin(Stdio::piped())
-                .stdout(Stdio::piped())
-                .stderr(Stdio::piped())
-                .kill_on_drop(true)
-                .spawn()?,
-            Self::GitReceivePack { repository } => Command::new("git-receive-pack")
-                .env_clear()
-                .envs(envs)
-                .arg(repository.to_path(storage))
-                .stdin(Stdio::piped())
-                .stdout(Stdio::piped())
-                .stderr(Stdio::piped())
-                .kill_on_drop(true)
-                .spawn()?,
-        };
-
-        let (mut stdin, mut stdout) = (
-            child.stdin.take().map(CompatExt::compat),
-            child
-                .stdout
-                .take()
-                .expect("Unable to take service's `stdout` handle")
-                .compat(),
-        );
-
         let (mut reader, mut writer) = (channel.as_reader(), channel.as_writer());
         request.accept().await?;
 
-        let (status, _, _) = tokio::try_join!(
-            // Wait for `child process` to exit.
-            async move {
-                let Output { status, stderr, .. } = child.wait_with_output().await?;
-
-                if !stderr.is_empty() {
-                    tracing::warn!(
-                        "Service additionnal output (code {}): {}",
-                        status.code().unwrap_or(i32::MAX),
-                        String::from_utf8_lossy(&stderr)
-                    );
-                }
+        // Verify the requested format before handing the arguments to `git-upload-archive`,
+        // since the built-in formats cannot be disabled through it's configuration.
+        let mut arguments = Vec::new();
+        if let Self::GitUploadArchive { .. } = self {
+            let format = read_arguments(&mut reader, &mut arguments).await?;
 
-                Ok::<_, eyre::Error>(status)
-            },
-            // Process `peer -> child process` communication.
-            async move {
-                let mut buf = [0u8; 4096 * 8];
+            if !archive.allows(&format) {
+                let reason = format!("NACK the `{format}` format is not allowed");
+                writer
+                    .write_all(format!("{:04x}{reason}\n0000", reason.len() + 5).as_bytes())
+                    .await?;
+                writer.flush().await?;
 
+                return Ok(ExitStatus::from_raw(1 << 8));
+            }
+        }
+
+        let child = match self {
+            Self::GitUploadPack { repository } => process::spawn(
+                Command::new("git-upload-pack")
+                    .env_clear()
+                    .envs(envs)
+                    .arg("--strict")
+                    .arg("--timeout=3")
+                    .arg(repository.to_path(storage)),
+            )?,
+            Self::GitReceivePack { repository } => process::spawn(
+                Command::new("git-receive-pack")
+                    .env_clear()
+                    .envs(envs)
+                    .arg(repository.to_path(storage)),
+            )?,
+            Self::GitUploadArchive { repository } => process::spawn(
+                Command::new("git-upload-archive")
+                    .env_clear()
+                    .envs(envs)
+                    .env("GIT_CONFIG_COUNT", "1")
+                    .env("GIT_CONFIG_KEY_0", "uploadarchive.allowUnreachable")
+                    .env("GIT_CONFIG_VALUE_0", archive.allow_unreachable.to_string())
+                    .arg(repository.to_path(storage)),
+            )?,
+            // Served by ourselves, through the multicall binary.
+            Self::GitLfsTransfer {
+                repository,
+                operation,
+            } => process::spawn(
+                Command::new(std::env::args().next().expect("The env contains no arg0"))
+                    .arg0("git-lfs-transfer")
+                    .env_clear()
+                    .envs(envs)
+                    .arg(repository.to_string())
+                    .arg(operation.to_string()),
+            )?,
+        };
+
+        let status = process::pipe(
+            child,
+            |mut stdin| async move {
+                stdin.write_all(&arguments).await?;
+
+                let mut buf = [0u8; 4096 * 8];
                 loop {
                     let n = reader.read(&mut buf[..]).await?;
                     if n == 0 {
-                        drop(stdin.take());
-
                         break Ok(());
                     }
 
-                    if let Some(ref mut stdin) = stdin {
-                        stdin.write_all(&buf[..n]).await?;
-                        stdin.flush().await?;
-                    }
+                    stdin.write_all(&buf[..n]).await?;
+                    stdin.flush().await?;
                 }
             },
-            // Process `child process -> peer` communication.
-            async move {
+            |mut stdout| async move {
                 let mut buf = [0u8; 4096 * 8];
-
                 loop {
                     let n = stdout.read(&mut buf[..]).await?;
                     if n == 0 {
@@ -135,9 +159,65 @@ impl Service {
                     writer.write_all(&buf[..n]).await?;
                     writer.flush().await?;
                 }
-            }
-        )?;
+            },
+        )
+        .await?;
 
         Ok(status)
     }
 }
+
+/// Read the `argument` pkt-lines sent by `git archive --remote` into `buffer` until the _flush-pkt_,
+/// returning the requested archive format.
+async fn read_arguments(
+    reader: &mut (impl AsyncRead + Unpin),
+    buffer: &mut Vec<u8>,
+) -> eyre::Result<String> {
+    let mut arguments = Vec::new();
+
+    loop {
+        let mut length = [0; 4];
+        reader.read_exact(&mut length).await?;
+        buffer.extend_from_slice(&length);
+
+        let length = std::str::from_utf8(&length)
+            .ok()
+            .and_then(|length| usize::from_str_radix(length, 16).ok())
+            .filter(|length| *length == 0 || *length > 4)
+            .ok_or_else(|| eyre::eyre!("Received an invalid pkt-line length"))?;
+        if length == 0 {
+            break;
+        }
+
+        let mut payload = vec![0; length - 4];
+        reader.read_exact(&mut payload).await?;
+        buffer.extend_from_slice(&payload);
+
+        if let Some(argument) = String::from_utf8_lossy(&payload).strip_prefix("argument ") {
+            arguments.push(argument.trim_end_matches('\n').to_owned());
+        }
+    }
+
+    // The last format wins, as with the option parsing of `git archive`,
+    // which also accepts the abbreviations of the long options, such as `--form=zip`.
+    let mut format = "tar".to_owned();
+    let mut arguments = arguments.iter();
+    while let Some(argument) = arguments.next() {
+        let (name, value) = match argument.split_once('=') {
+            Some((name, value)) => (name, Some(value)),
+            None => (argument.as_str(), None),
+        };
+
+        if name == "--" {
+            break;
+        } else if name.len() > 2 && "--format".starts_with(name) {
+            if let Some(value) = value.or_else(|| arguments.next().map(String::as_str)) {
+                format = value.into();
+            }
+        } else if name.len() > 5 && "--no-format".starts_with(name) {
+            format = "tar".into();
+        }
+    }
+
+    Ok(format)
+}
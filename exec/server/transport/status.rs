@@ -0,0 +1,154 @@
+use std::{
+    path::Path,
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use color_eyre::eyre;
+use ssh_key::PublicKey;
+
+use furrow::{
+    authority,
+    entries::Role,
+    status::{State, Status, Statuses},
+    Id, Repository,
+};
+
+use super::{
+    access::{allows, lookup},
+    service::ServiceAccess,
+};
+
+/// A command reading or reporting commit statuses, sent by the client as:
+///
+/// - `status get <repository> <revision>`
+/// - `status set <repository> <revision> <context> <state> [description]`
+#[derive(Debug)]
+pub enum StatusCommand {
+    /// List the statuses of the commit pointed by the `revision`.
+    Get { repository: Id, revision: String },
+
+    /// Set the status of the commit pointed by the `revision` in the `context`.
+    Set {
+        repository: Id,
+        revision: String,
+        context: String,
+        state: State,
+        description: Option<String>,
+    },
+}
+
+impl FromStr for StatusCommand {
+    type Err = eyre::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let usage = || {
+            eyre::eyre!(
+                "usage: status get <repository> <revision>\n       status set <repository> <revision> <context> <pending|success|failure|error> [description]"
+            )
+        };
+
+        let mut args = s.split_whitespace();
+        let action = args.next().ok_or_else(usage)?;
+        let repository = args.next().ok_or_else(usage)?.trim_matches('\'').parse()?;
+        let revision = args.next().ok_or_else(usage)?.into();
+
+        match action {
+            "get" if args.next().is_none() => Ok(Self::Get {
+                repository,
+                revision,
+            }),
+            "set" => {
+                let context = args.next().ok_or_else(usage)?.into();
+                let state = args
+                    .next()
+                    .ok_or_else(usage)?
+                    .parse()
+                    .map_err(|_| usage())?;
+                let description = args.collect::<Vec<_>>().join(" ");
+
+                Ok(Self::Set {
+                    repository,
+                    revision,
+                    context,
+                    state,
+                    description: (!description.is_empty()).then_some(description),
+                })
+            }
+            _ => Err(usage()),
+        }
+    }
+}
+
+impl StatusCommand {
+    /// Execute the command on behalf of the `key`, returning the output for the client.
+    pub fn exec(&self, storage: &Path, key: &PublicKey) -> eyre::Result<String> {
+        let (Self::Get {
+            repository: id,
+            revision,
+        }
+        | Self::Set {
+            repository: id,
+            revision,
+            ..
+        }) = self;
+
+        let (authority::Local { keychain, .. }, spec) = lookup(storage, id)?;
+
+        let allowed = match self {
+            // Anyone able to read the repository may read it's statuses.
+            Self::Get { .. } => {
+                keychain.has_role(Role::Status, key)
+                    || allows(&keychain, &spec, ServiceAccess::Read, Some(key))
+            }
+            Self::Set { .. } => keychain.has_role(Role::Status, key),
+        };
+        if !allowed {
+            eyre::bail!("The access to the statuses of `{id}` has been denied");
+        }
+
+        let repository = Repository::open(storage, id)?;
+        let commit = repository.revparse_single(revision)?.peel_to_commit()?.id();
+
+        match self {
+            Self::Get { .. } => {
+                let statuses = Statuses::load(&repository, commit)?;
+
+                Ok(statuses
+                    .0
+                    .iter()
+                    .map(|(context, status)| {
+                        format!(
+                            "{:<8} {context}\t{}\n",
+                            status.state.to_string(),
+                            status.description.as_deref().unwrap_or_default()
+                        )
+                    })
+                    .collect())
+            }
+            Self::Set {
+                context,
+                state,
+                description,
+                ..
+            } => {
+                Statuses::set(
+                    &repository,
+                    commit,
+                    context,
+                    Status {
+                        state: *state,
+                        description: description.clone(),
+                        updated: SystemTime::now()
+                            .duration_since(UNIX_EPOCH)
+                            .unwrap_or_default()
+                            .as_secs() as i64,
+                        reporter: key.fingerprint(Default::default()).to_string(),
+                    },
+                )?;
+
+                Ok(format!("Set `{context}` to `{state}` on {commit}\n"))
+            }
+        }
+    }
+}
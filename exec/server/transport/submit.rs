@@ -0,0 +1,278 @@
+use std::{
+    path::Path,
+    process::Stdio,
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use async_compat::CompatExt;
+use color_eyre::eyre;
+use futures::AsyncWriteExt;
+use ssh_key::PublicKey;
+use tokio::process::Command;
+
+use furrow::{
+    authority,
+    entries::{Keychain, Role, Spec, Visibility},
+    proposal::{Proposal, Series, State},
+    Id, Repository,
+};
+
+use super::access::lookup;
+use crate::hooks::{
+    io::{Ref, RefUpdate},
+    pre_receive,
+};
+
+/// The maximum size of a patch series, as read from the client.
+pub const MAX_SERIES: u64 = 16 * 1024 * 1024;
+
+/// A command submitting a patch series produced by `git format-patch` as a proposal,
+/// sent by the client with the series as input as `submit <repository> [branch]`.
+#[derive(Debug)]
+pub struct SubmitCommand {
+    repository: Id,
+    branch: Option<String>,
+}
+
+impl FromStr for SubmitCommand {
+    type Err = eyre::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let usage = || eyre::eyre!("usage: submit <repository> [branch] < series.mbox");
+
+        let mut args = s.split_whitespace();
+        let repository = args.next().ok_or_else(usage)?.trim_matches('\'').parse()?;
+        let branch = args.next().map(|branch| branch.trim_matches('\'').into());
+
+        if args.next().is_some() {
+            return Err(usage());
+        }
+
+        Ok(Self { repository, branch })
+    }
+}
+
+impl SubmitCommand {
+    /// Execute the command on behalf of the `key` with the `mbox` series,
+    /// returning the output for the client.
+    pub async fn exec(&self, storage: &Path, key: &PublicKey, mbox: &[u8]) -> eyre::Result<String> {
+        let id = &self.repository;
+
+        let (authority::Local { keychain, .. }, spec) = lookup(storage, id)?;
+
+        // Anyone may submit to public repositories, as with mailing-lists.
+        let allowed = match spec.visibility {
+            Visibility::Private => keychain.has_role(Role::Propose, key),
+            Visibility::Public => true,
+            Visibility::Archive => false,
+        } && spec.upstream.is_none();
+        if !allowed {
+            eyre::bail!("The submission of proposals to `{id}` has been denied");
+        }
+
+        let series = std::str::from_utf8(mbox)
+            .ok()
+            .and_then(Series::parse)
+            .ok_or_else(|| {
+                eyre::eyre!("The input is not a patch series, use `git format-patch --stdout`")
+            })?;
+
+        let repository = Repository::open(storage, id)?;
+        let target = match &self.branch {
+            Some(branch) => branch.clone(),
+            None => repository
+                .find_reference("HEAD")?
+                .symbolic_target()
+                .and_then(|target| target.strip_prefix("refs/heads/"))
+                .ok_or_else(|| eyre::eyre!("The repository `{id}` has no default branch"))?
+                .into(),
+        };
+        if repository
+            .find_reference(&format!("refs/heads/{target}"))
+            .is_err()
+        {
+            eyre::bail!("The target branch `{target}` does not exist");
+        }
+
+        let directory =
+            std::env::temp_dir().join(format!("furrow-submit-{:08x}", rand::random::<u32>()));
+        let result = self.apply(storage, &directory, &target, mbox).await;
+
+        let result = match result {
+            Ok(()) => {
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs() as i64;
+                let mut proposal = Proposal {
+                    number: 0,
+                    target,
+                    topic: None,
+                    author: key.fingerprint(Default::default()).to_string(),
+                    created: now,
+                    updated: now,
+                    state: State::Open,
+                    series: Some(series),
+                };
+                proposal.reserve(&repository)?;
+
+                // Import the applied series from the clone under the proposal's reference,
+                // only saving the proposal once it's changes are stored.
+                match self
+                    .store(&directory, &repository, &proposal, &spec, &keychain, key)
+                    .await
+                {
+                    Ok(()) => proposal
+                        .save(&repository)
+                        .map(|()| proposal)
+                        .map_err(Into::into),
+                    Err(err) => {
+                        if let Err(err) = proposal.release(&repository) {
+                            tracing::warn!(
+                                "Unable to release the proposal #{} of `{id}`: {err}",
+                                proposal.number
+                            );
+                        }
+
+                        Err(err)
+                    }
+                }
+            }
+            Err(err) => Err(err),
+        };
+
+        match std::fs::remove_dir_all(&directory) {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => tracing::warn!(
+                "Unable to cleanup the submission directory `{}`: {err}",
+                directory.display()
+            ),
+            _ => (),
+        }
+
+        let proposal = result?;
+
+        Ok(format!(
+            "Created proposal #{} for `{}`, at `{}`\n",
+            proposal.number,
+            proposal.target,
+            proposal.refname()
+        ))
+    }
+
+    /// Fetch the applied series from the clone in `directory` under the `proposal`'s reference,
+    /// removing it unless it's content passes the checks of a push by the `key`.
+    async fn store(
+        &self,
+        directory: &Path,
+        repository: &Repository,
+        proposal: &Proposal,
+        spec: &Spec,
+        keychain: &Keychain,
+        key: &PublicKey,
+    ) -> eyre::Result<()> {
+        let refname = proposal.refname();
+
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(repository.path())
+            .args(["fetch", "--quiet", "--no-write-fetch-head", "--"])
+            .arg(directory)
+            .arg(format!("+HEAD:{refname}"))
+            .kill_on_drop(true)
+            .output()
+            .await?;
+        if !output.status.success() {
+            eyre::bail!(
+                "Unable to store the series: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+
+        // The series doesn't go through the hooks, check it as they would for a push.
+        let update = RefUpdate {
+            oldrev: git2::Oid::zero(),
+            newrev: repository.refname_to_id(&refname)?,
+            refname: Ref::Other(refname.clone()),
+        };
+        if let Err(err) = pre_receive::check_content(repository, &update, spec, keychain, key) {
+            repository.find_reference(&refname)?.delete()?;
+
+            return Err(err.into());
+        }
+
+        Ok(())
+    }
+
+    /// Apply the `mbox` series on top of the `target` branch in a fresh clone of the repository in `directory`.
+    async fn apply(
+        &self,
+        storage: &Path,
+        directory: &Path,
+        target: &str,
+        mbox: &[u8],
+    ) -> eyre::Result<()> {
+        let output = Command::new("git")
+            .args(["clone", "--quiet", "--shared", "--no-checkout", "--"])
+            .arg(self.repository.to_path(storage))
+            .arg(directory)
+            .kill_on_drop(true)
+            .output()
+            .await?;
+        if !output.status.success() {
+            eyre::bail!(
+                "Unable to clone the repository: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(directory)
+            .args(["checkout", "--quiet", "--detach"])
+            .arg(format!("origin/{target}"))
+            .kill_on_drop(true)
+            .output()
+            .await?;
+        if !output.status.success() {
+            eyre::bail!(
+                "Unable to checkout `{target}`: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+
+        let mut child = Command::new("git")
+            .arg("-C")
+            .arg(directory)
+            .args(["am", "--quiet", "--empty=drop"])
+            .env_clear()
+            .env("PATH", std::env::var_os("PATH").unwrap_or_default())
+            .env("HOME", directory)
+            .env("GIT_COMMITTER_NAME", "furrow")
+            .env("GIT_COMMITTER_EMAIL", "git@server.commit")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()?;
+
+        let mut stdin = child
+            .stdin
+            .take()
+            .expect("Unable to take the `git am` `stdin` handle")
+            .compat();
+        stdin.write_all(mbox).await?;
+        drop(stdin);
+
+        let output = child.wait_with_output().await?;
+        if !output.status.success() {
+            eyre::bail!(
+                "The series does not apply on `{target}`:\n{}{}",
+                String::from_utf8_lossy(&output.stdout),
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+
+        Ok(())
+    }
+}
@@ -10,16 +10,9 @@ use assh_connect::channel::{
     Channel,
 };
 use color_eyre::eyre::{self, WrapErr};
-use futures::TryStreamExt;
+use futures::{AsyncReadExt, AsyncWriteExt, TryStreamExt};
 
-use furrow::{
-    authority,
-    entries::{RegistrationPolicy, Visibility},
-    id::Kind,
-    Id, Repository,
-};
-
-use super::{service::ServiceAccess, GitConfig, Service};
+use super::{authorize, command, submit, Command, GitConfig, Service};
 use crate::{hooks::Hooks, server::Socket};
 
 /// A tunnel a request is operated in,
@@ -29,7 +22,7 @@ pub struct Tunnel<'f> {
     gitconfig: &'f GitConfig,
 
     channel: Channel<'f, Socket, Server>,
-    key: &'f PublicKey,
+    key: Option<&'f PublicKey>,
 }
 
 impl<'f> Tunnel<'f> {
@@ -37,7 +30,7 @@ impl<'f> Tunnel<'f> {
         storage: &'f Path,
         gitconfig: &'f GitConfig,
         channel: Channel<'f, Socket, Server>,
-        key: &'f PublicKey,
+        key: Option<&'f PublicKey>,
     ) -> Self {
         Self {
             storage,
@@ -83,6 +76,30 @@ impl<'f> Tunnel<'f> {
 
                     let command =
                         str::from_utf8(command).wrap_err("Received a non-utf8 service request")?;
+
+                    if let Some(command) = Command::parse(command) {
+                        request.accept().await?;
+
+                        // Read the client's input until it's end, such as a patch series.
+                        let mut input = Vec::new();
+                        if matches!(&command, Ok(command) if command.reads_input()) {
+                            self.channel
+                                .as_reader()
+                                .take(submit::MAX_SERIES)
+                                .read_to_end(&mut input)
+                                .await?;
+                        }
+
+                        let output = match command {
+                            Ok(command) => command.exec(self.storage, self.key, &input).await,
+                            Err(err) => Err(err),
+                        };
+
+                        self.respond(output).await?;
+
+                        break;
+                    }
+
                     let service = command
                         .parse()
                         .wrap_err_with(|| format!("Unable to parse service-request: {command}"))?;
@@ -93,6 +110,14 @@ impl<'f> Tunnel<'f> {
 
                     break;
                 }
+                ChannelRequestContext::Shell => {
+                    drop(requests);
+
+                    request.accept().await?;
+                    self.respond(Ok(command::greeting(self.key))).await?;
+
+                    break;
+                }
                 msg => tracing::trace!("Received an unhandled message: {:?}", msg),
             }
         }
@@ -100,6 +125,29 @@ impl<'f> Tunnel<'f> {
         Ok(())
     }
 
+    /// Send the `output` of a command to the client, or it's error, along with the exit status,
+    /// once it's request has been accepted.
+    async fn respond(&self, output: eyre::Result<String>) -> eyre::Result<()> {
+        let (output, code) = match output {
+            Ok(output) => (output, 0),
+            Err(err) => {
+                tracing::warn!("Unable to process command request: {err:#}");
+
+                (format!("error: {err}\n"), 1)
+            }
+        };
+
+        let mut writer = self.channel.as_writer();
+        writer.write_all(output.as_bytes()).await?;
+        writer.flush().await?;
+
+        self.channel
+            .request(ChannelRequestContext::ExitStatus { code })
+            .await?;
+
+        Ok(())
+    }
+
     /// Process the service request from the requested service
     /// and the acquired context.
     async fn exec(
@@ -110,71 +158,32 @@ impl<'f> Tunnel<'f> {
     ) -> eyre::Result<()> {
         tracing::info!("Received new service request: {service}");
 
-        // Open the global authority repository.
-        let global = Repository::open(self.storage, &Id::global_authority())
-            .or_else(|_| Repository::init(self.storage, &Id::global_authority()))?;
-
-        // Load or init the global authority from the repository.
-        let authority = authority::Global::load_or_init(&global, self.key)?;
-
-        // Automatically create the local authority repository if self-registration
-        // is allowed or the requester is from the global authority keychain.
-        if service.target().kind() == Kind::LocalAuthority
-            && (authority.global.registration == RegistrationPolicy::Allow
-                || authority.local.keychain.contains(self.key))
-        {
-            Repository::open(self.storage, service.target())
-                .or_else(|_| Repository::init(self.storage, service.target()))?;
-        }
-
-        // Load or init the target authority from the repository.
-        let authority = match service.target().kind() {
-            Kind::GlobalAuthority => authority.local,
-            _ => {
-                let repository = Repository::open(self.storage, &service.target().to_authority())?;
-
-                authority::Local::load_or_init(&repository, self.key)?
-            }
-        };
-
-        let allowed = if service.target().is_authority() {
-            authority.keychain.contains(self.key)
-        } else {
-            let repository = authority
-                .repositories
-                .get(service.target().repository())
-                .ok_or_else(|| {
-                    eyre::eyre!("Missing repository definition for `{}`", service.target())
-                })?;
-
-            let allowed = match repository.visibility {
-                Visibility::Private => authority.keychain.contains(self.key),
-                Visibility::Public => {
-                    service.access() == ServiceAccess::Read || authority.keychain.contains(self.key)
-                }
-                Visibility::Archive => service.access() == ServiceAccess::Read,
-            };
-
-            if allowed {
-                // Create the target repository if non-existant.
-                Repository::open(self.storage, service.target())
-                    .or_else(|_| Repository::init(self.storage, service.target()))?;
-            }
-
-            allowed
-        };
+        // Anonymous clients are only ever allowed to read, such as LFS downloads,
+        // and as such never run the hooks.
+        //
+        // The authorization may wait on the lock of the authorities to initialize them,
+        // so run it off the async workers.
+        let (storage, target, access, key) = (
+            self.storage.to_owned(),
+            service.target().clone(),
+            service.access(),
+            self.key.cloned(),
+        );
+        let (allowed, archive) =
+            tokio::task::spawn_blocking(move || authorize(&storage, &target, access, key.as_ref()))
+                .await??;
 
         if allowed {
             // Install our server-side hooks and inject env variables
             Hooks::install(self.storage, service.target())?;
-            Hooks::env(&mut envs, self.storage, service.target());
+            Hooks::env(&mut envs, self.storage, service.target(), self.key)?;
 
             // Install our own `.gitconfig`
             self.gitconfig.env(&mut envs);
 
             // Execute the git service
             if let Ok(status) = service
-                .exec(&envs, self.storage, &self.channel, request)
+                .exec(&envs, self.storage, &archive, &self.channel, request)
                 .await
                 .wrap_err("Service request transfer failed")
             {
@@ -0,0 +1,155 @@
+use std::{
+    path::{Path, PathBuf},
+    process::Stdio,
+    time::Duration,
+};
+
+use async_compat::CompatExt;
+use async_trait::async_trait;
+use color_eyre::eyre;
+use futures::{io::BufReader, AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::{net::TcpStream, process::Command};
+
+use furrow::{
+    entries::{Entry, Global},
+    Id, Repository,
+};
+
+use super::Worker;
+use crate::hooks::post_receive::emails::{Email, QUEUE};
+
+/// The maximum duration of an exchange with the SMTP relay.
+const SMTP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The [`Worker`] sending the emails, using either `sendmail` or an SMTP relay.
+pub struct Emails {
+    storage: PathBuf,
+}
+
+impl Emails {
+    pub fn new(storage: PathBuf) -> Self {
+        Self { storage }
+    }
+}
+
+#[async_trait]
+impl Worker for Emails {
+    type Item = Email;
+
+    const QUEUE: &'static str = QUEUE;
+
+    async fn process(&self, email: &Self::Item) -> eyre::Result<()> {
+        tracing::debug!("Sending email to {}", email.recipients.join(", "));
+
+        // Load the configuration on each email, to follow changes of the transport.
+        let global = Global::load(&Repository::open(&self.storage, &Id::global_authority())?)?;
+        let Some(mail) = global.mail else {
+            eyre::bail!("The outgoing mail has been disabled since the email was queued");
+        };
+
+        match &mail.relay {
+            Some(relay) => tokio::time::timeout(SMTP_TIMEOUT, smtp(relay, email))
+                .await
+                .map_err(|_| eyre::eyre!("The SMTP relay `{relay}` timed out"))?,
+            None => {
+                sendmail(
+                    mail.sendmail.as_deref().unwrap_or(Path::new("sendmail")),
+                    email,
+                )
+                .await
+            }
+        }
+    }
+}
+
+/// Hand the `email` to the `sendmail`-compatible binary at `path`.
+async fn sendmail(path: &Path, email: &Email) -> eyre::Result<()> {
+    let mut child = Command::new(path)
+        .args(["-i", "-f", &email.from, "--"])
+        .args(&email.recipients)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+
+    let mut stdin = child
+        .stdin
+        .take()
+        .expect("Unable to take the `sendmail` `stdin` handle")
+        .compat();
+    stdin.write_all(email.message.as_bytes()).await?;
+    drop(stdin);
+
+    let output = child.wait_with_output().await?;
+    if !output.status.success() {
+        eyre::bail!(
+            "`{}` failed: {}",
+            path.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    Ok(())
+}
+
+/// Send the `email` through the SMTP `relay`, without authentication nor encryption.
+async fn smtp(relay: &str, email: &Email) -> eyre::Result<()> {
+    let mut stream = BufReader::new(TcpStream::connect(relay).await?.compat());
+    let domain = email
+        .from
+        .rsplit_once('@')
+        .map_or("localhost", |(_, domain)| domain);
+
+    reply(&mut stream, b'2').await?;
+    command(&mut stream, &format!("EHLO {domain}"), b'2').await?;
+    command(&mut stream, &format!("MAIL FROM:<{}>", email.from), b'2').await?;
+    for recipient in &email.recipients {
+        command(&mut stream, &format!("RCPT TO:<{recipient}>"), b'2').await?;
+    }
+    command(&mut stream, "DATA", b'3').await?;
+
+    // Normalize line endings and escape the lines starting with a dot.
+    let mut data = String::with_capacity(email.message.len());
+    for line in email.message.lines() {
+        if line.starts_with('.') {
+            data.push('.');
+        }
+        data.push_str(line);
+        data.push_str("\r\n");
+    }
+    stream.write_all(data.as_bytes()).await?;
+    command(&mut stream, ".", b'2').await?;
+
+    command(&mut stream, "QUIT", b'2').await
+}
+
+/// Send an SMTP `line` and wait for a reply of the `class`.
+async fn command(
+    stream: &mut (impl AsyncBufRead + AsyncWrite + Unpin),
+    line: &str,
+    class: u8,
+) -> eyre::Result<()> {
+    stream.write_all(format!("{line}\r\n").as_bytes()).await?;
+    stream.flush().await?;
+
+    reply(stream, class).await
+}
+
+/// Read a complete, possibly multiline, SMTP reply and ensure it is of the `class`.
+async fn reply(stream: &mut (impl AsyncBufRead + Unpin), class: u8) -> eyre::Result<()> {
+    loop {
+        let mut line = String::new();
+        if stream.read_line(&mut line).await? == 0 {
+            eyre::bail!("The SMTP relay closed the connection unexpectedly");
+        }
+
+        if line.as_bytes().first() != Some(&class) {
+            eyre::bail!("The SMTP relay replied with an error: {}", line.trim());
+        }
+
+        if line.as_bytes().get(3) != Some(&b'-') {
+            return Ok(());
+        }
+    }
+}
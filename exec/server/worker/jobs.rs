@@ -0,0 +1,267 @@
+use std::{
+    path::{Path, PathBuf},
+    process::Stdio,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use async_compat::CompatExt;
+use async_trait::async_trait;
+use color_eyre::eyre;
+use futures::AsyncReadExt;
+use tokio::process::Command;
+
+use furrow::{
+    status::{self, Status, Statuses},
+    Repository,
+};
+
+use super::Worker;
+use crate::hooks::post_receive::jobs::{Results, Run, State, QUEUE};
+
+/// The maximum size of the output kept for a job run, the oldest output being discarded.
+const MAX_OUTPUT: usize = 64 * 1024;
+
+/// The delay given to read the remaining output of a command, after it's group has been killed.
+const OUTPUT_GRACE: Duration = Duration::from_secs(5);
+
+/// The [`Worker`] running the jobs in a fresh checkout of the commit, using `sh` in the `sandbox`.
+///
+/// The jobs run arbitrary commands from the pushed commits, they are thus refused
+/// unless the operator has configured a `sandbox` program isolating them from the server.
+pub struct Jobs {
+    storage: PathBuf,
+    sandbox: Option<PathBuf>,
+}
+
+impl Jobs {
+    pub fn new(storage: PathBuf, sandbox: Option<PathBuf>) -> Self {
+        Self { storage, sandbox }
+    }
+
+    /// Update the result of the job `run` with the `state` and `output`.
+    fn record(&self, run: &Run, state: State, output: Option<String>) -> eyre::Result<()> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let path = Results::path(&self.storage, &run.id, run.commit);
+        let mut results = Results::load(&path)?;
+
+        if let Some(result) = results.0.get_mut(&run.job.name) {
+            result.state = state;
+
+            match state {
+                State::Running => result.started = Some(now),
+                State::Success | State::Failure | State::Error => result.finished = Some(now),
+                State::Pending => (),
+            }
+
+            if let Some(output) = output {
+                result.output = output;
+            }
+        }
+
+        results.save(&path)?;
+
+        // Report the state as the `ci/<job>` commit status.
+        let (state, description) = match state {
+            State::Pending => (status::State::Pending, "The job is queued"),
+            State::Running => (status::State::Pending, "The job is running"),
+            State::Success => (status::State::Success, "The job succeeded"),
+            State::Failure => (status::State::Failure, "The job failed"),
+            State::Error => (status::State::Error, "The job couldn't be run"),
+        };
+        Statuses::set(
+            &Repository::open(&self.storage, &run.id)?,
+            run.commit,
+            &format!("ci/{}", run.job.name),
+            Status {
+                state,
+                description: Some(description.into()),
+                updated: now as i64,
+                reporter: "ci".into(),
+            },
+        )
+        .map_err(Into::into)
+    }
+
+    /// Clone the repository in the `directory`, checkout the commit and run the commands in order,
+    /// returning the final state and the combined output.
+    async fn execute(
+        &self,
+        run: &Run,
+        sandbox: &Path,
+        directory: &Path,
+    ) -> eyre::Result<(State, String)> {
+        let deadline = Instant::now() + Duration::from_secs(run.job.timeout);
+
+        // Copy the objects rather than sharing or hard-linking them,
+        // for the checkout not to reference nor expose the storage to the job.
+        let output = Command::new("git")
+            .args(["clone", "--quiet", "--no-hardlinks", "--no-checkout", "--"])
+            .arg(run.id.to_path(&self.storage))
+            .arg(directory)
+            .kill_on_drop(true)
+            .output()
+            .await?;
+        if !output.status.success() {
+            eyre::bail!(
+                "Unable to clone the repository: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(directory)
+            .args(["checkout", "--quiet", "--detach"])
+            .arg(run.commit.to_string())
+            .kill_on_drop(true)
+            .output()
+            .await?;
+        if !output.status.success() {
+            eyre::bail!(
+                "Unable to checkout `{}`: {}",
+                run.commit,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+
+        let mut log = String::new();
+        for command in &run.job.run {
+            log.push_str(&format!("$ {command}\n"));
+
+            let mut child = Command::new(sandbox)
+                .arg(directory)
+                .args(["sh", "-c"])
+                .arg(format!("exec 2>&1\n{command}"))
+                .current_dir(directory)
+                .env_clear()
+                .env("PATH", std::env::var_os("PATH").unwrap_or_default())
+                .env("HOME", directory)
+                .env("CI", "true")
+                .env("FURROW_REPOSITORY", run.id.to_string())
+                .env("FURROW_REF", &run.refname)
+                .env("FURROW_COMMIT", run.commit.to_string())
+                .env("FURROW_JOB", &run.job.name)
+                .stdin(Stdio::null())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::null())
+                .process_group(0)
+                .kill_on_drop(true)
+                .spawn()?;
+            let group = child.id();
+
+            // Read the output in the background, since it may be held open by the processes
+            // left behind by the command, until their group is killed.
+            let mut stdout = child.stdout.take().map(CompatExt::compat);
+            let reader = tokio::spawn(async move {
+                let mut output = Vec::new();
+                if let Some(stdout) = &mut stdout {
+                    let _ = stdout.read_to_end(&mut output).await;
+                }
+
+                output
+            });
+
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let status = tokio::time::timeout(remaining, child.wait()).await;
+
+            // Kill the whole process group, not to leave behind the processes
+            // started by a timed out command, or daemonized by a finished one.
+            if let Some(group) = group.and_then(|group| libc::pid_t::try_from(group).ok()) {
+                // SAFETY: `killpg` has no memory-safety requirements,
+                // and the group was created for the command by `process_group(0)`.
+                unsafe { libc::killpg(group, libc::SIGKILL) };
+            }
+            drop(child);
+
+            let output = tokio::time::timeout(OUTPUT_GRACE, reader)
+                .await
+                .ok()
+                .and_then(Result::ok)
+                .unwrap_or_default();
+            log.push_str(&String::from_utf8_lossy(&output));
+
+            let Ok(status) = status else {
+                log.push_str(&format!("\nThe job timed out after {}s\n", run.job.timeout));
+
+                return Ok((State::Failure, truncate(log)));
+            };
+            let status = status?;
+
+            if !status.success() {
+                log.push_str(&format!("\nThe command failed with {status}\n"));
+
+                return Ok((State::Failure, truncate(log)));
+            }
+        }
+
+        Ok((State::Success, truncate(log)))
+    }
+}
+
+#[async_trait]
+impl Worker for Jobs {
+    type Item = Run;
+
+    const QUEUE: &'static str = QUEUE;
+
+    const MAX_ATTEMPTS: u32 = 3;
+
+    async fn process(&self, run: &Self::Item) -> eyre::Result<()> {
+        tracing::debug!(
+            "Running job `{}` on `{}` in `{}`",
+            run.job.name,
+            run.commit,
+            run.id
+        );
+
+        let Some(sandbox) = &self.sandbox else {
+            return self.record(
+                run,
+                State::Error,
+                Some(
+                    "The jobs are disabled on this server, which has no sandbox to run them".into(),
+                ),
+            );
+        };
+
+        self.record(run, State::Running, None)?;
+
+        let directory =
+            std::env::temp_dir().join(format!("furrow-job-{:08x}", rand::random::<u32>()));
+        let result = self.execute(run, sandbox, &directory).await;
+
+        match std::fs::remove_dir_all(&directory) {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => tracing::warn!(
+                "Unable to cleanup the job directory `{}`: {err}",
+                directory.display()
+            ),
+            _ => (),
+        }
+
+        match result {
+            Ok((state, output)) => self.record(run, state, Some(output)),
+            Err(err) => {
+                self.record(run, State::Error, Some(format!("{err:#}")))?;
+
+                Err(err)
+            }
+        }
+    }
+}
+
+/// Truncate the `log` to it's last [`MAX_OUTPUT`] bytes.
+fn truncate(log: String) -> String {
+    if log.len() <= MAX_OUTPUT {
+        return log;
+    }
+
+    let mut start = log.len() - MAX_OUTPUT;
+    while !log.is_char_boundary(start) {
+        start += 1;
+    }
+
+    format!("[..]\n{}", &log[start..])
+}
@@ -0,0 +1,108 @@
+use std::{
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use async_trait::async_trait;
+use color_eyre::eyre;
+use tokio::process::Command;
+
+use furrow::Repository;
+
+use super::Worker;
+use crate::hooks::post_receive::mirrors::{MirrorStatus, Status, Sync, QUEUE};
+
+/// The directory containing the credentials used to push to mirrors, relative to the `storage` path.
+pub const CREDENTIALS_PATH: &str = ".credentials";
+
+/// The [`Worker`] pushing references to the mirrors, using `git push`.
+pub struct Mirrors {
+    storage: PathBuf,
+}
+
+impl Mirrors {
+    pub fn new(storage: PathBuf) -> Self {
+        Self { storage }
+    }
+
+    async fn push(&self, sync: &Sync) -> eyre::Result<()> {
+        let repository = Repository::open(&self.storage, &sync.id)?;
+
+        // Push the current state of the references rather than the pushed revisions,
+        // to stay correct when tasks are retried after subsequent pushes.
+        let refspecs: Vec<_> = sync
+            .refs
+            .iter()
+            .map(|refname| match repository.find_reference(refname) {
+                Ok(_) => format!("+{refname}:{refname}"),
+                Err(_) => format!(":{refname}"),
+            })
+            .collect();
+
+        let mut command = Command::new("git");
+        super::restrict(&mut command)
+            .env("GIT_TERMINAL_PROMPT", "0")
+            .arg("--git-dir")
+            .arg(sync.id.to_path(&self.storage))
+            .args(["push", "--porcelain", "--"])
+            .arg(sync.mirror.url.as_str())
+            .args(refspecs)
+            .kill_on_drop(true);
+
+        if let Some(credentials) = &sync.mirror.credentials {
+            let directory = self.storage.join(CREDENTIALS_PATH);
+
+            command.env(
+                "GIT_SSH_COMMAND",
+                format!(
+                    "ssh -i '{}' -o IdentitiesOnly=yes -o StrictHostKeyChecking=accept-new -o UserKnownHostsFile='{}'",
+                    directory.join(&**credentials).display(),
+                    directory.join("known_hosts").display()
+                ),
+            );
+        }
+
+        let output = command.output().await?;
+        if !output.status.success() {
+            eyre::bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Worker for Mirrors {
+    type Item = Sync;
+
+    const QUEUE: &'static str = QUEUE;
+
+    async fn process(&self, sync: &Self::Item) -> eyre::Result<()> {
+        tracing::debug!("Mirroring `{}` to `{}`", sync.id, sync.mirror.url);
+
+        let result = self.push(sync).await;
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let path = Status::path(&self.storage, &sync.id);
+        let mut status = Status::load(&path)?;
+        let entry = status
+            .0
+            .entry(sync.mirror.url.to_string())
+            .or_insert_with(MirrorStatus::default);
+
+        entry.last_attempt = now;
+        match &result {
+            Ok(()) => {
+                entry.last_success = Some(now);
+                entry.error = None;
+            }
+            Err(err) => entry.error = Some(format!("{err:#}")),
+        }
+        status.save(&path)?;
+
+        result.map_err(|err| eyre::eyre!("Mirroring to `{}` failed: {err:#}", sync.mirror.url))
+    }
+}
@@ -0,0 +1,102 @@
+//! Types and structs related to _background processing_ of the tasks queued by the hooks,
+//! and of the periodic maintenance of the repositories.
+
+use std::{path::Path, time::Duration};
+
+use async_trait::async_trait;
+use color_eyre::eyre;
+use serde::{de::DeserializeOwned, Serialize};
+
+use furrow::{entries::Remote, queue::Queue};
+
+mod emails;
+pub use emails::Emails;
+
+mod jobs;
+pub use jobs::Jobs;
+
+mod mirrors;
+pub use mirrors::Mirrors;
+
+mod upstreams;
+pub use upstreams::Upstreams;
+
+mod webhooks;
+pub use webhooks::Webhooks;
+
+/// The interval at which the queues are polled for ready tasks.
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// The delay before the first retry of a failed task, doubled on each attempt.
+const RETRY_DELAY: Duration = Duration::from_secs(30);
+
+/// Restrict the `git` command to the network transports allowed for a [`Remote`],
+/// so that it can never read nor write the server's own storage through a local URL.
+fn restrict(command: &mut tokio::process::Command) -> &mut tokio::process::Command {
+    command
+        .env("GIT_ALLOW_PROTOCOL", Remote::PROTOCOLS.join(":"))
+        .env("GIT_PROTOCOL_FROM_USER", "0")
+        .args(["-c", "protocol.file.allow=never"])
+}
+
+/// A background worker, processing the tasks of a [`Queue`].
+#[async_trait]
+pub trait Worker: Send + Sync + 'static {
+    /// The type of the tasks processed by the worker.
+    type Item: Serialize + DeserializeOwned + Send + Sync;
+
+    /// The name of the [`Queue`] the worker processes.
+    const QUEUE: &'static str;
+
+    /// The maximum count of attempts at processing a task before discarding it.
+    const MAX_ATTEMPTS: u32 = 8;
+
+    /// Process a single task from the queue.
+    async fn process(&self, item: &Self::Item) -> eyre::Result<()>;
+}
+
+/// Spawn the `worker` in the background, processing the tasks of it's queue in the `storage` path.
+pub fn spawn<W: Worker>(worker: W, storage: &Path) {
+    let queue = Queue::<W::Item>::new(storage, W::QUEUE);
+
+    tokio::spawn(async move {
+        loop {
+            if let Err(err) = poll(&worker, &queue).await {
+                tracing::error!("Unable to poll the `{}` queue: {err}", W::QUEUE);
+            }
+
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    });
+}
+
+/// Process all the ready tasks of the `queue` with the `worker`.
+async fn poll<W: Worker>(worker: &W, queue: &Queue<W::Item>) -> eyre::Result<()> {
+    for task in queue.ready()? {
+        match worker.process(&task.item).await {
+            Ok(()) => queue.complete(task)?,
+            Err(err) if task.attempts + 1 >= W::MAX_ATTEMPTS => {
+                tracing::error!(
+                    "Discarding task from the `{}` queue after {} attempts: {err:#}",
+                    W::QUEUE,
+                    task.attempts + 1
+                );
+
+                queue.complete(task)?
+            }
+            Err(err) => {
+                let delay = RETRY_DELAY * 2u32.pow(task.attempts);
+
+                tracing::warn!(
+                    "Task from the `{}` queue failed, retrying in {}s: {err:#}",
+                    W::QUEUE,
+                    delay.as_secs()
+                );
+
+                queue.retry(task, delay)?
+            }
+        }
+    }
+
+    Ok(())
+}
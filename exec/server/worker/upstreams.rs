@@ -0,0 +1,322 @@
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use color_eyre::eyre;
+use tokio::process::Command;
+
+use furrow::{
+    authority,
+    entries::{Entry, Repositories, Upstream},
+    Id, Repository,
+};
+
+use crate::hooks::post_receive::mirrors::MirrorStatus;
+
+/// The name of the file holding the [`MirrorStatus`] of the upstream, in the repository.
+pub const STATUS_PATH: &str = "upstream.json";
+
+/// The interval at which the repositories are checked for due fetches.
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The periodic worker fetching the repositories tracking an [`Upstream`], using `git fetch`.
+pub struct Upstreams {
+    storage: PathBuf,
+}
+
+impl Upstreams {
+    pub fn new(storage: PathBuf) -> Self {
+        Self { storage }
+    }
+
+    /// Spawn the worker in the background, checking the repositories periodically.
+    pub fn spawn(self) {
+        tokio::spawn(async move {
+            loop {
+                if let Err(err) = self.check().await {
+                    tracing::error!("Unable to check the repositories for upstreams: {err:#}");
+                }
+
+                tokio::time::sleep(CHECK_INTERVAL).await;
+            }
+        });
+    }
+
+    /// Fetch all the repositories of all the namespaces for which the upstream is due.
+    async fn check(&self) -> eyre::Result<()> {
+        for namespace in authority::namespaces(&self.storage)? {
+            let repositories = match Repository::open(
+                &self.storage,
+                &Id::new(namespace.clone(), furrow::AUTHORITY_REPOSITORY_NAME),
+            )
+            .map_err(Into::into)
+            .and_then(|repository| Repositories::load(&repository).map_err(eyre::Error::from))
+            {
+                Ok(repositories) => repositories,
+                Err(err) => {
+                    tracing::warn!(
+                        "Skipping namespace `{}` with an unreadable authority: {err:#}",
+                        namespace.as_deref().unwrap_or("/")
+                    );
+
+                    continue;
+                }
+            };
+
+            for (name, spec) in repositories.iter() {
+                let Some(upstream) = &spec.upstream else {
+                    continue;
+                };
+
+                let id = Id::new(namespace.clone(), name.clone());
+                if let Err(err) = self.sync(&id, upstream).await {
+                    tracing::error!("Unable to synchronize `{id}` from it's upstream: {err:#}");
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Fetch the repository from the `upstream` if due, and record the result.
+    async fn sync(&self, id: &Id, upstream: &Upstream) -> eyre::Result<()> {
+        let path = id.to_path(&self.storage).join(STATUS_PATH);
+        let mut status = MirrorStatus::load(&path)?;
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        if status.last_attempt + upstream.interval > now {
+            return Ok(());
+        }
+
+        tracing::debug!("Fetching `{id}` from `{}`", upstream.url);
+
+        Repository::open(&self.storage, id).or_else(|_| Repository::init(&self.storage, id))?;
+
+        let result = self.fetch(id, upstream).await;
+
+        status.last_attempt = now;
+        match &result {
+            Ok(()) => {
+                status.last_success = Some(now);
+                status.error = None;
+            }
+            Err(err) => status.error = Some(format!("{err:#}")),
+        }
+        status.save(&path)?;
+
+        result
+    }
+
+    /// Replicate the branches and tags of the `upstream` into the repository,
+    /// along with it's default branch.
+    async fn fetch(&self, id: &Id, upstream: &Upstream) -> eyre::Result<()> {
+        let path = id.to_path(&self.storage);
+
+        git(&path, |command| {
+            command
+                .args(["fetch", "--prune", "--no-write-fetch-head", "--"])
+                .arg(upstream.url.as_str())
+                .args(["+refs/heads/*:refs/heads/*", "+refs/tags/*:refs/tags/*"])
+        })
+        .await?;
+
+        let remote = git(&path, |command| {
+            command
+                .args(["ls-remote", "--symref", "--"])
+                .arg(upstream.url.as_str())
+                .arg("HEAD")
+        })
+        .await?;
+
+        // Follow the default branch of the upstream, as in `ref: refs/heads/main\tHEAD`.
+        if let Some(head) = remote
+            .lines()
+            .find_map(|line| line.strip_prefix("ref: ")?.strip_suffix("\tHEAD"))
+        {
+            let repository = Repository::open(&self.storage, id)?;
+
+            if repository.find_reference(head).is_ok() {
+                repository.set_head(head)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Run a `git` command against the repository at `path`, returning it's standard output.
+async fn git(path: &Path, args: impl FnOnce(&mut Command) -> &mut Command) -> eyre::Result<String> {
+    let mut command = Command::new("git");
+    super::restrict(&mut command)
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .arg("--git-dir")
+        .arg(path)
+        .kill_on_drop(true);
+
+    let output = args(&mut command).output().await?;
+    if !output.status.success() {
+        eyre::bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+
+    /// Create a temporary directory holding the `storage` and an `upstream` repository,
+    /// with a commit on it's `trunk` default branch.
+    fn fixture() -> (PathBuf, git2::Oid) {
+        let root = std::env::temp_dir().join(format!(
+            "furrow-upstreams-{}-{}",
+            std::process::id(),
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .expect("The clock is before the epoch")
+                .as_nanos()
+        ));
+
+        let upstream = git2::Repository::init_bare(root.join("upstream.git"))
+            .expect("Unable to init the upstream");
+        let tree = upstream
+            .find_tree(
+                upstream
+                    .treebuilder(None)
+                    .and_then(|tree| tree.write())
+                    .expect("Unable to write the tree"),
+            )
+            .expect("Unable to find the tree");
+        let signature =
+            git2::Signature::now("t", "t@example.com").expect("Unable to create the signature");
+        let commit = upstream
+            .commit(
+                Some("refs/heads/trunk"),
+                &signature,
+                &signature,
+                "Initial",
+                &tree,
+                &[],
+            )
+            .expect("Unable to commit");
+        upstream
+            .set_head("refs/heads/trunk")
+            .expect("Unable to set the `HEAD`");
+
+        (root, commit)
+    }
+
+    /// Serve the repositories of the `root` directory with `git daemon`, returning it's port.
+    async fn serve(root: &Path) -> (tokio::process::Child, u16) {
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .and_then(|listener| listener.local_addr())
+            .expect("Unable to find a free port")
+            .port();
+
+        // Spawn `git-daemon` itself rather than through `git`, for it to be killed on drop.
+        let exec = std::process::Command::new("git")
+            .arg("--exec-path")
+            .output()
+            .expect("Unable to find the `git` programs");
+        let exec = String::from_utf8_lossy(&exec.stdout);
+
+        let daemon = Command::new(Path::new(exec.trim()).join("git-daemon"))
+            .args(["--export-all", "--listen=127.0.0.1"])
+            .arg(format!("--port={port}"))
+            .arg(format!("--base-path={}", root.display()))
+            .arg(root)
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .kill_on_drop(true)
+            .spawn()
+            .expect("Unable to spawn `git daemon`");
+
+        let start = Instant::now();
+        while tokio::net::TcpStream::connect(("127.0.0.1", port))
+            .await
+            .is_err()
+        {
+            assert!(
+                start.elapsed() < Duration::from_secs(10),
+                "`git daemon` didn't start"
+            );
+
+            tokio::time::sleep(Duration::from_millis(25)).await;
+        }
+
+        (daemon, port)
+    }
+
+    #[tokio::test]
+    async fn it_fetches_the_upstream() {
+        let (root, commit) = fixture();
+        let (_daemon, port) = serve(&root).await;
+
+        let storage = root.join("storage");
+        let id: Id = "repo.git".parse().expect("Invalid id");
+        let upstream = Upstream {
+            url: format!("git://127.0.0.1:{port}/upstream.git")
+                .parse()
+                .expect("Invalid remote"),
+            interval: Upstream::DEFAULT_INTERVAL,
+        };
+
+        Upstreams::new(storage.clone())
+            .sync(&id, &upstream)
+            .await
+            .expect("Unable to sync");
+
+        let repository = Repository::open(&storage, &id).expect("Unable to open the repository");
+        assert_eq!(
+            repository.refname_to_id("refs/heads/trunk").ok(),
+            Some(commit)
+        );
+        assert_eq!(
+            repository
+                .find_reference("HEAD")
+                .ok()
+                .and_then(|head| head.symbolic_target().map(Into::into)),
+            Some("refs/heads/trunk".to_string())
+        );
+
+        let status = MirrorStatus::load(&id.to_path(&storage).join(STATUS_PATH))
+            .expect("Unable to load the status");
+        assert!(status.error.is_none());
+        assert_eq!(status.last_success, Some(status.last_attempt));
+
+        std::fs::remove_dir_all(root).expect("Unable to clean up the fixture");
+    }
+
+    #[tokio::test]
+    async fn it_records_the_failures() {
+        let (root, _) = fixture();
+
+        let storage = root.join("storage");
+        let id: Id = "repo.git".parse().expect("Invalid id");
+        let upstream = Upstream {
+            url: "git://127.0.0.1:1/upstream.git"
+                .parse()
+                .expect("Invalid remote"),
+            interval: Upstream::DEFAULT_INTERVAL,
+        };
+        let upstreams = Upstreams::new(storage.clone());
+
+        assert!(upstreams.sync(&id, &upstream).await.is_err());
+
+        let path = id.to_path(&storage).join(STATUS_PATH);
+        let status = MirrorStatus::load(&path).expect("Unable to load the status");
+        assert!(status.error.is_some());
+        assert!(status.last_success.is_none());
+
+        // The next attempt is only due after the interval.
+        assert!(upstreams.sync(&id, &upstream).await.is_ok());
+
+        std::fs::remove_dir_all(root).expect("Unable to clean up the fixture");
+    }
+}
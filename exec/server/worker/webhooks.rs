@@ -0,0 +1,68 @@
+use std::process::Stdio;
+
+use async_compat::CompatExt;
+use async_trait::async_trait;
+use color_eyre::eyre;
+use futures::AsyncWriteExt;
+use tokio::process::Command;
+
+use super::Worker;
+use crate::hooks::post_receive::webhooks::{Delivery, QUEUE};
+
+/// The [`Worker`] delivering the webhooks payloads, using `curl`.
+pub struct Webhooks;
+
+#[async_trait]
+impl Worker for Webhooks {
+    type Item = Delivery;
+
+    const QUEUE: &'static str = QUEUE;
+
+    async fn process(&self, delivery: &Self::Item) -> eyre::Result<()> {
+        tracing::debug!(
+            "Delivering `{}` event to webhook `{}`",
+            delivery.event,
+            delivery.url
+        );
+
+        let mut command = Command::new("curl");
+        command
+            .env_clear()
+            .args(["--silent", "--show-error", "--fail", "--max-time", "10"])
+            .args(["--proto", "=http,https", "--proto-redir", "=http,https"])
+            .args(["--request", "POST", "--data-binary", "@-"])
+            .args(["--header", "Content-Type: application/json"])
+            .args(["--header", &format!("X-Furrow-Event: {}", delivery.event)]);
+
+        if let Some(signature) = &delivery.signature {
+            command.args(["--header", &format!("X-Furrow-Signature-256: {signature}")]);
+        }
+
+        let mut child = command
+            .args(["--url", &delivery.url])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()?;
+
+        let mut stdin = child
+            .stdin
+            .take()
+            .expect("Unable to take the `curl` `stdin` handle")
+            .compat();
+        stdin.write_all(delivery.payload.as_bytes()).await?;
+        drop(stdin);
+
+        let output = child.wait_with_output().await?;
+        if !output.status.success() {
+            eyre::bail!(
+                "Delivery to `{}` failed: {}",
+                delivery.url,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+
+        Ok(())
+    }
+}
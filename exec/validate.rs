@@ -0,0 +1,134 @@
+//! Offline validation of the entries of an _authority repository_,
+//! as the server would when they are pushed.
+
+use std::path::{Path, PathBuf};
+
+use clap::Parser;
+use color_eyre::eyre;
+
+use furrow::{
+    authority,
+    entries::{self, Repositories},
+    id::Base,
+    Id,
+};
+
+/// Validate a working tree or a commit of an authority repository, as the server would on push.
+#[derive(Debug, Parser)]
+#[command(rename_all = "kebab-case")]
+pub struct Validate {
+    /// The path of the authority repository's working tree, or of any directory in it.
+    #[arg(default_value = ".")]
+    path: PathBuf,
+
+    /// Validate the entries of the commit pointed by the revision, instead of the working tree.
+    #[arg(long)]
+    revision: Option<String>,
+
+    /// The revision the entries are compared to, to find the removed repositories,
+    /// defaulting to `HEAD` for the working tree, and to the parent of the `--revision`.
+    #[arg(long)]
+    base: Option<String>,
+
+    /// The namespace of the authority repository, validated as the global one if unset.
+    #[arg(long)]
+    namespace: Option<Base>,
+
+    /// The storage directory of the server, to verify that the removed repositories are empty.
+    #[arg(long)]
+    storage: Option<PathBuf>,
+}
+
+/// Where the entries are read from.
+enum Source<'r> {
+    WorkTree(PathBuf),
+    Commit(&'r git2::Repository, git2::Tree<'r>),
+}
+
+impl Source<'_> {
+    /// Read the document at `path`, or [`None`] if it doesn't exist.
+    fn read(&self, path: &str) -> Result<Option<String>, entries::ErrorKind> {
+        match self {
+            Self::WorkTree(root) => match std::fs::read_to_string(root.join(path)) {
+                Ok(content) => Ok(Some(content)),
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+                Err(err) => Err(err.into()),
+            },
+            Self::Commit(repository, tree) => authority::read_tree(repository, tree, path),
+        }
+    }
+}
+
+impl Validate {
+    pub fn run(&self) -> eyre::Result<()> {
+        let repository = git2::Repository::discover(&self.path).ok();
+        let repository = || {
+            repository
+                .as_ref()
+                .ok_or_else(|| eyre::eyre!("`{}` is not a git repository", self.path.display()))
+        };
+        let revision = |revision: &str| -> eyre::Result<git2::Commit<'_>> {
+            Ok(repository()?.revparse_single(revision)?.peel_to_commit()?)
+        };
+
+        let (source, base) = match &self.revision {
+            Some(rev) => {
+                let commit = revision(rev)?;
+                let base = match &self.base {
+                    Some(base) => Some(revision(base)?),
+                    None => commit.parent(0).ok(),
+                };
+
+                (Source::Commit(repository()?, commit.tree()?), base)
+            }
+            None => {
+                let base = match &self.base {
+                    Some(base) => Some(revision(base)?),
+                    None => revision("HEAD").ok(),
+                };
+
+                // Read the entries from the root of the working tree, as they are committed,
+                // even when the path is a sub-directory of it.
+                let root = repository()
+                    .ok()
+                    .and_then(git2::Repository::workdir)
+                    .map_or_else(|| self.path.clone(), Path::to_path_buf);
+
+                (Source::WorkTree(root), base)
+            }
+        };
+        let base = base
+            .map(|commit| Ok::<_, eyre::Error>(Source::Commit(repository()?, commit.tree()?)))
+            .transpose()?;
+
+        // Compare with the base to find the removed repositories, which must be empty.
+        let current = base.as_ref().and_then(|base| {
+            authority::parse::<_, Repositories>(&|path| base.read(path))
+                .inspect_err(|err| {
+                    eprintln!("warning: Unable to compare with the base entries: {err}")
+                })
+                .ok()
+        });
+
+        let new = authority::verify(
+            self.namespace.as_ref(),
+            |path| source.read(path),
+            self.storage.as_deref(),
+            current.as_ref(),
+        )?;
+
+        if self.storage.is_none() {
+            for name in current.iter().flat_map(|current| current.removed(&new)) {
+                let id = Id::new(self.namespace.clone(), name.clone());
+
+                eprintln!(
+                    "warning: The repository `{id}` is removed, which is rejected on push unless it's empty"
+                );
+            }
+        }
+
+        println!("The authority entries are valid");
+
+        Ok(())
+    }
+}
@@ -1,13 +1,127 @@
 //! Definitions of the different kinds of _authority repositories_.
 
+use std::{io, path::Path};
+
 use git2::Oid;
 use ssh_key::PublicKey;
+use thiserror::Error;
 
 use super::{
-    entries::{self, Entry},
-    Repository,
+    entries::{self, Entry, Transaction},
+    id::Base,
+    Id, Repository,
 };
 
+/// List the namespaces in the `storage` path which have an authority repository,
+/// starting with the _global_ namespace.
+pub fn namespaces(storage: &Path) -> io::Result<Vec<Option<Base>>> {
+    let mut namespaces = vec![None];
+
+    for entry in std::fs::read_dir(storage)? {
+        let entry = entry?;
+
+        if !entry.file_type()?.is_dir() {
+            continue;
+        }
+
+        let Some(namespace) = entry
+            .file_name()
+            .to_str()
+            .and_then(|name| name.parse().ok())
+        else {
+            continue;
+        };
+
+        let namespace = Some(namespace);
+        if Id::new(namespace.clone(), crate::AUTHORITY_REPOSITORY_NAME)
+            .to_path(storage)
+            .is_dir()
+        {
+            namespaces.push(namespace);
+        }
+    }
+
+    namespaces.sort_by(|a, b| a.as_deref().cmp(&b.as_deref()));
+
+    Ok(namespaces)
+}
+
+/// An [`enum@Error`] that can occur while verifying the entries of an authority repository.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// An entry is missing or invalid.
+    #[error("Unable to parse {0}")]
+    Entry(#[from] entries::Error),
+
+    /// A removed repository still has some references.
+    #[error("The repository `{0}` is not empty, and thus cannot be removed.")]
+    NonEmptyRepository(Id),
+
+    /// A _git repository_ error.
+    #[error(transparent)]
+    Git(#[from] git2::Error),
+}
+
+/// Verify the entries of the authority repository in the `namespace`, as `read` from their paths,
+/// returning the repositories they define.
+///
+/// When both the `storage` and the `current` repositories are provided,
+/// the repositories removed from the `current` ones are required to be empty.
+pub fn verify(
+    namespace: Option<&Base>,
+    read: impl Fn(&'static str) -> Result<Option<String>, entries::ErrorKind>,
+    storage: Option<&Path>,
+    current: Option<&entries::Repositories>,
+) -> Result<entries::Repositories, Error> {
+    // The global entries are only defined in the global authority.
+    if namespace.is_none() {
+        parse::<_, entries::Global>(&read)?;
+    }
+    parse::<_, entries::Keychain>(&read)?;
+    let repositories = parse::<_, entries::Repositories>(&read)?;
+
+    if let (Some(storage), Some(current)) = (storage, current) {
+        for name in current.removed(&repositories) {
+            let id = Id::new(namespace.cloned(), name.clone());
+            let repository = match Repository::open(storage, &id) {
+                Err(err) if err.code() == git2::ErrorCode::NotFound => continue,
+                other => other?,
+            };
+
+            if !repository.is_empty()? {
+                return Err(Error::NonEmptyRepository(id));
+            }
+        }
+    }
+
+    Ok(repositories)
+}
+
+/// Read the document at `path` in the `tree` of the `repository`, or [`None`] if it doesn't exist.
+pub fn read_tree(
+    repository: &git2::Repository,
+    tree: &git2::Tree<'_>,
+    path: &str,
+) -> Result<Option<String>, entries::ErrorKind> {
+    let entry = match tree.get_path(Path::new(path)) {
+        Err(err) if err.code() == git2::ErrorCode::NotFound => return Ok(None),
+        other => other?,
+    };
+    let blob = entry.to_object(repository)?.peel_to_blob()?;
+
+    Ok(Some(std::str::from_utf8(blob.content())?.to_owned()))
+}
+
+/// Read and parse the `T` entry, which is required to exist.
+pub fn parse<A, T: Entry<A>>(
+    read: &impl Fn(&'static str) -> Result<Option<String>, entries::ErrorKind>,
+) -> Result<T, entries::Error> {
+    read(T::PATH)
+        .map_err(entries::Error::new::<A, T>)?
+        .ok_or_else(|| entries::Error::new::<A, T>(entries::ErrorKind::Missing))
+        .and_then(|content| T::parse(&content))
+}
+
 /// Authority repository _entries_ in the _global_ namespace.
 pub struct Global {
     /// Global entries for server-wide configuration.
@@ -18,14 +132,48 @@ pub struct Global {
 }
 
 impl Global {
-    /// Load the entries from the `repository` or init them from the provided arguments.
+    /// Load the entries from the `repository` or init them from the provided arguments,
+    /// committing all the initialized entries at once.
     pub fn load_or_init(repository: &Repository, key: &PublicKey) -> Result<Self, entries::Error> {
+        // Only lock the repository when some entries are to be initialized.
+        match Self::load(repository) {
+            Err(err) if err.is_missing() => (),
+            other => return other,
+        }
+
+        let mut transaction = Transaction::begin(repository)?;
+        let authority = Self {
+            global: transaction.load_or_init(())?,
+            local: Local::load_or_stage(&mut transaction, key)?,
+        };
+
+        transaction.commit(
+            "Initialization of the global authority",
+            &entries::signature().map_err(entries::Error::head)?,
+        )?;
+
+        Ok(authority)
+    }
+
+    /// Load the entries from the `repository`, without initializing them.
+    pub fn load(repository: &Repository) -> Result<Self, entries::Error> {
         Ok(Self {
-            global: Entry::load_or_init(repository, ())?,
-            local: Local::load_or_init(repository, key)?,
+            global: Entry::load(repository)?,
+            local: Local::load(repository)?,
         })
     }
 
+    /// Migrate the entries of the `repository` to the current versions of their schemas,
+    /// committing them at once and returning the descriptions of the migrations.
+    pub fn migrate(repository: &Repository) -> Result<Vec<String>, entries::Error> {
+        let mut transaction = Transaction::begin(repository)?;
+
+        let mut migrations = Local::stage_migrations(&mut transaction)?;
+        migrations.extend(migration::<_, entries::Global>(&mut transaction)?);
+
+        commit_migrations(transaction, migrations)
+    }
+
     /// Load the entries from the `repository` at the provided `reference`.
     pub fn load_at(repository: &Repository, reference: Oid) -> Result<Self, entries::Error> {
         Ok(Self {
@@ -45,14 +193,61 @@ pub struct Local {
 }
 
 impl Local {
-    /// Load the entries from the `repository` or init them from the provided arguments.
+    /// Load the entries from the `repository` or init them from the provided arguments,
+    /// committing all the initialized entries at once.
     pub fn load_or_init(repository: &Repository, key: &PublicKey) -> Result<Self, entries::Error> {
+        // Only lock the repository when some entries are to be initialized.
+        match Self::load(repository) {
+            Err(err) if err.is_missing() => (),
+            other => return other,
+        }
+
+        let mut transaction = Transaction::begin(repository)?;
+        let authority = Self::load_or_stage(&mut transaction, key)?;
+
+        transaction.commit(
+            "Initialization of the namespace authority",
+            &entries::signature().map_err(entries::Error::head)?,
+        )?;
+
+        Ok(authority)
+    }
+
+    /// Load the entries from the `transaction` or stage their initialization from the provided arguments.
+    pub fn load_or_stage(
+        transaction: &mut Transaction<'_>,
+        key: &PublicKey,
+    ) -> Result<Self, entries::Error> {
         Ok(Self {
-            keychain: Entry::load_or_init(repository, key)?,
-            repositories: Entry::load_or_init(repository, ())?,
+            keychain: transaction.load_or_init(key)?,
+            repositories: transaction.load_or_init(())?,
         })
     }
 
+    /// Load the entries from the `repository`, without initializing them.
+    pub fn load(repository: &Repository) -> Result<Self, entries::Error> {
+        Ok(Self {
+            keychain: Entry::load(repository)?,
+            repositories: Entry::load(repository)?,
+        })
+    }
+
+    /// Migrate the entries of the `repository` to the current versions of their schemas,
+    /// committing them at once and returning the descriptions of the migrations.
+    pub fn migrate(repository: &Repository) -> Result<Vec<String>, entries::Error> {
+        let mut transaction = Transaction::begin(repository)?;
+        let migrations = Self::stage_migrations(&mut transaction)?;
+
+        commit_migrations(transaction, migrations)
+    }
+
+    fn stage_migrations(transaction: &mut Transaction<'_>) -> Result<Vec<String>, entries::Error> {
+        Ok(migration::<_, entries::Keychain>(transaction)?
+            .into_iter()
+            .chain(migration::<_, entries::Repositories>(transaction)?)
+            .collect())
+    }
+
     /// Load the entries from the `repository` at the provided `reference`.
     pub fn load_at(repository: &Repository, reference: Oid) -> Result<Self, entries::Error> {
         Ok(Self {
@@ -61,3 +256,30 @@ impl Local {
         })
     }
 }
+
+/// Stage the migration of the `T` entry in the `transaction`, returning it's description if any.
+fn migration<A, T: Entry<A>>(
+    transaction: &mut Transaction<'_>,
+) -> Result<Option<String>, entries::Error> {
+    Ok(transaction
+        .migrate::<A, T>()?
+        .map(|version| format!("`{}` from version {version} to {}", T::PATH, T::VERSION)))
+}
+
+/// Commit the staged `migrations` of the `transaction`, if any, describing them in the message.
+fn commit_migrations(
+    transaction: Transaction<'_>,
+    migrations: Vec<String>,
+) -> Result<Vec<String>, entries::Error> {
+    let message = migrations.iter().fold(
+        "Migration of the configuration files\n".to_owned(),
+        |message, migration| message + "\n- " + migration,
+    );
+
+    transaction.commit(
+        &message,
+        &entries::signature().map_err(entries::Error::head)?,
+    )?;
+
+    Ok(migrations)
+}
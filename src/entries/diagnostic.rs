@@ -0,0 +1,221 @@
+//! Human-readable diagnostics of the entries' parsing errors.
+
+use std::{fmt, ops::Range};
+
+/// A parsing error of an entry's document, located in it's content,
+/// with a suggestion for the misspelled fields and variants.
+#[derive(Debug)]
+pub struct Diagnostic {
+    path: &'static str,
+    message: String,
+    location: Option<Box<Location>>,
+    suggestion: Option<String>,
+}
+
+/// The location of a [`Diagnostic`] in the document.
+#[derive(Debug)]
+struct Location {
+    line: usize,
+    column: usize,
+    snippet: String,
+    width: usize,
+}
+
+impl Diagnostic {
+    /// Create a diagnostic of the deserialization `err` of the `content` at `path`.
+    pub fn new(path: &'static str, content: &str, err: &toml::de::Error) -> Self {
+        Self::build(path, content, err.message(), err.span())
+    }
+
+    /// Create a diagnostic of the parsing `err` of the `content` at `path`.
+    pub fn document(path: &'static str, content: &str, err: &toml_edit::TomlError) -> Self {
+        Self::build(path, content, err.message(), err.span())
+    }
+
+    fn build(path: &'static str, content: &str, message: &str, span: Option<Range<usize>>) -> Self {
+        let location = span
+            .filter(|span| content.is_char_boundary(span.start))
+            .map(|span| {
+                let prefix = &content[..span.start];
+                let start = prefix.rfind('\n').map_or(0, |index| index + 1);
+                let snippet = content[start..]
+                    .lines()
+                    .next()
+                    .unwrap_or_default()
+                    .trim_end_matches('\r');
+                let column = prefix[start..].chars().count();
+
+                Box::new(Location {
+                    line: prefix.matches('\n').count() + 1,
+                    column: column + 1,
+                    width: content
+                        .get(span)
+                        .map_or(1, |spanned| spanned.chars().count())
+                        .clamp(1, snippet.chars().count().saturating_sub(column).max(1)),
+                    snippet: snippet.to_owned(),
+                })
+            });
+
+        Self {
+            path,
+            message: message.trim_end().to_owned(),
+            location,
+            suggestion: suggest(message),
+        }
+    }
+
+    /// The line of the error in the document, starting from `1`.
+    pub fn line(&self) -> Option<usize> {
+        self.location.as_ref().map(|location| location.line)
+    }
+
+    /// The column of the error in the document, starting from `1`.
+    pub fn column(&self) -> Option<usize> {
+        self.location.as_ref().map(|location| location.column)
+    }
+
+    /// The known name closest to the unknown one, if any.
+    pub fn suggestion(&self) -> Option<&str> {
+        self.suggestion.as_deref()
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)?;
+
+        if let Some(location) = &self.location {
+            let gutter = " ".repeat(location.line.to_string().len());
+
+            write!(
+                f,
+                "\n{gutter}--> {}:{}:{}",
+                self.path, location.line, location.column
+            )?;
+            write!(f, "\n{gutter} |")?;
+            write!(f, "\n{} | {}", location.line, location.snippet)?;
+            write!(
+                f,
+                "\n{gutter} | {}{}",
+                " ".repeat(location.column - 1),
+                "^".repeat(location.width)
+            )?;
+        }
+
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, "\nhelp: did you mean `{suggestion}`?")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for Diagnostic {}
+
+/// Find the closest expected name to the unknown one of the `serde` message, such as
+/// ``unknown field `visibilty`, expected one of `description`, `visibility` ``.
+fn suggest(message: &str) -> Option<String> {
+    let rest = message
+        .strip_prefix("unknown field `")
+        .or_else(|| message.strip_prefix("unknown variant `"))?;
+    let (unknown, rest) = rest.split_once('`')?;
+    let (_, expected) = rest.split_once("expected")?;
+
+    expected
+        .split('`')
+        .skip(1)
+        .step_by(2)
+        .map(|name| (distance(unknown, name), name))
+        .filter(|(distance, name)| *distance <= (name.chars().count() / 3).max(1))
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, name)| name.to_owned())
+}
+
+/// The Levenshtein distance between `a` and `b`.
+fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, a) in a.chars().enumerate() {
+        let mut previous = row[0];
+        row[0] = i + 1;
+
+        for (j, b) in b.iter().enumerate() {
+            let current = row[j + 1];
+            row[j + 1] = if a == *b {
+                previous
+            } else {
+                previous.min(row[j]).min(current) + 1
+            };
+            previous = current;
+        }
+    }
+
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Debug, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    #[allow(dead_code)]
+    struct Sample {
+        #[serde(default)]
+        visibility: Option<Kind>,
+        #[serde(default)]
+        description: Option<String>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    enum Kind {
+        Public,
+        Private,
+    }
+
+    fn diagnose(content: &str) -> Diagnostic {
+        let err = toml::from_str::<Sample>(content).expect_err("The content should be invalid");
+
+        Diagnostic::new("Sample.toml", content, &err)
+    }
+
+    #[rstest]
+    #[case::field("# Comment\nvisibilty = \"public\"\n", 2, 1, Some("visibility"))]
+    #[case::variant(
+        "description = \"a\"\nvisibility = \"publik\"\n",
+        2,
+        14,
+        Some("public")
+    )]
+    #[case::unrelated("unrelated = 1\n", 1, 1, None)]
+    fn diagnostics_are_located(
+        #[case] content: &str,
+        #[case] line: usize,
+        #[case] column: usize,
+        #[case] suggestion: Option<&str>,
+    ) {
+        let diagnostic = diagnose(content);
+
+        assert_eq!(diagnostic.line(), Some(line));
+        assert_eq!(diagnostic.column(), Some(column));
+        assert_eq!(diagnostic.suggestion(), suggestion);
+    }
+
+    #[test]
+    fn diagnostics_are_rendered() {
+        assert_eq!(
+            diagnose("# Comment\nvisibilty = \"public\"\n").to_string(),
+            "unknown field `visibilty`, expected `visibility` or `description`\n \
+             --> Sample.toml:2:1\n  \
+             |\n\
+             2 | visibilty = \"public\"\n  \
+             | ^^^^^^^^^\n\
+             help: did you mean `visibility`?"
+        );
+    }
+}
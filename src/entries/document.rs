@@ -0,0 +1,214 @@
+//! Format-preserving edition of the entries' TOML documents.
+
+use toml_edit::{DocumentMut, Item, Table, TableLike};
+
+/// Apply the changes between the `old` and the `new` serializations of an entry
+/// to the `document` it was loaded from, preserving it's comments, formatting and ordering.
+///
+/// Returns [`None`] if any of the documents could not be parsed.
+pub fn patch(document: &str, old: &str, new: &str) -> Option<String> {
+    let mut document: DocumentMut = document.parse().ok()?;
+    let old: DocumentMut = old.parse().ok()?;
+    let new: DocumentMut = new.parse().ok()?;
+
+    patch_table(
+        document.as_table_mut(),
+        None,
+        old.as_table(),
+        new.as_table(),
+    );
+
+    Some(document.to_string())
+}
+
+/// Apply the changes between the `old` and `new` tables to the `target` table,
+/// which is at the `position` in the document, if any.
+fn patch_table(
+    target: &mut dyn TableLike,
+    position: Option<usize>,
+    old: &dyn TableLike,
+    new: &dyn TableLike,
+) {
+    for (key, _) in old.iter() {
+        if !new.contains_key(key) {
+            target.remove(key);
+        }
+    }
+
+    for (key, item) in new.iter() {
+        let previous = old.get(key);
+        if previous.is_some_and(|previous| same(previous, item)) {
+            continue;
+        }
+
+        match (target.get_mut(key), previous) {
+            (Some(target), Some(previous)) => patch_item(target, previous, item),
+            (Some(target), None) => replace(target, item),
+            (None, _) => {
+                let mut item = item.clone();
+
+                // Place the inserted tables right after their parent and it's other tables.
+                set_position(
+                    &mut item,
+                    position.max(last_position(target)).unwrap_or_default(),
+                );
+
+                target.insert(key, item);
+            }
+        }
+    }
+}
+
+/// Apply the changes between the `old` and `new` items to the `target` item.
+fn patch_item(target: &mut Item, old: &Item, new: &Item) {
+    match (target, old, new) {
+        (target, old, new)
+            if target.is_table_like() && old.is_table_like() && new.is_table_like() =>
+        {
+            let position = target.as_table().and_then(Table::position);
+
+            patch_table(
+                target.as_table_like_mut().expect("The item is a table"),
+                position,
+                old.as_table_like().expect("The item is a table"),
+                new.as_table_like().expect("The item is a table"),
+            )
+        }
+        (Item::ArrayOfTables(target), Item::ArrayOfTables(old), Item::ArrayOfTables(new))
+            if target.len() == old.len() && old.len() == new.len() =>
+        {
+            for ((target, old), new) in target.iter_mut().zip(old.iter()).zip(new.iter()) {
+                let position = target.position();
+
+                patch_table(target, position, old, new);
+            }
+        }
+        (target, _, new) => replace(target, new),
+    }
+}
+
+/// Replace the `target` item with the `new` one, keeping the decorations of the values.
+fn replace(target: &mut Item, new: &Item) {
+    let mut new = new.clone();
+
+    match (&*target, &mut new) {
+        (Item::Value(previous), Item::Value(value)) => {
+            *value.decor_mut() = previous.decor().clone();
+        }
+        (Item::Table(previous), item) => {
+            if let Some(position) = previous.position() {
+                set_position(item, position);
+            }
+        }
+        _ => (),
+    }
+
+    *target = new;
+}
+
+/// Compare two items produced by the same serializer, regardless of the ordering of the tables.
+fn same(a: &Item, b: &Item) -> bool {
+    match (a, b) {
+        (Item::ArrayOfTables(a), Item::ArrayOfTables(b)) => {
+            a.len() == b.len() && a.iter().zip(b.iter()).all(|(a, b)| same_table(a, b))
+        }
+        (a, b) if a.is_table_like() && b.is_table_like() => same_table(
+            a.as_table_like().expect("The item is a table"),
+            b.as_table_like().expect("The item is a table"),
+        ),
+        (Item::Value(a), Item::Value(b)) => a.to_string().trim() == b.to_string().trim(),
+        _ => false,
+    }
+}
+
+fn same_table(a: &dyn TableLike, b: &dyn TableLike) -> bool {
+    a.len() == b.len()
+        && a.iter()
+            .all(|(key, a)| b.get(key).is_some_and(|b| same(a, b)))
+}
+
+/// Find the greatest position of the tables in the `table`, including itself.
+fn last_position(table: &dyn TableLike) -> Option<usize> {
+    let tables = |item: &Item| -> Option<usize> {
+        match item {
+            Item::Table(table) => table.position().max(last_position(table)),
+            Item::ArrayOfTables(array) => array
+                .iter()
+                .filter_map(|table| table.position().max(last_position(table)))
+                .max(),
+            _ => None,
+        }
+    };
+
+    table.iter().filter_map(|(_, item)| tables(item)).max()
+}
+
+/// Set the position of the tables in the `item`, including itself.
+fn set_position(item: &mut Item, position: usize) {
+    let table = |table: &mut Table| {
+        table.set_position(position);
+
+        for (_, item) in table.iter_mut() {
+            set_position(item, position);
+        }
+    };
+
+    match item {
+        Item::Table(inner) => table(inner),
+        Item::ArrayOfTables(array) => array.iter_mut().for_each(table),
+        _ => (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+
+    #[rstest]
+    #[case::unchanged(
+        "# Comment\nb = 1 # trailing\na = [1, 2]\n",
+        "a = [1, 2]\nb = 1\n",
+        "a = [1, 2]\nb = 1\n",
+        "# Comment\nb = 1 # trailing\na = [1, 2]\n"
+    )]
+    #[case::changed(
+        "# Comment\nb = 1 # trailing\na = [1, 2]\n",
+        "a = [1, 2]\nb = 1\n",
+        "a = [1, 2]\nb = 2\n",
+        "# Comment\nb = 2 # trailing\na = [1, 2]\n"
+    )]
+    #[case::removed(
+        "# Comment\nb = 1\n# Kept\na = 1\n",
+        "a = 1\nb = 1\n",
+        "a = 1\n",
+        "# Kept\na = 1\n"
+    )]
+    #[case::defaults(
+        "[t]\nb = 1\n",
+        "[t]\nb = 1\nc = []\n",
+        "[t]\nb = 2\nc = []\n",
+        "[t]\nb = 2\n"
+    )]
+    #[case::nested(
+        "[x]\na = 1\n\n[y]\nc = 1\n",
+        "[x]\na = 1\n\n[y]\nc = 1\n",
+        "[x]\na = 1\n\n[x.new]\nd = 1\n\n[y]\nc = 1\n",
+        "[x]\na = 1\n\n[x.new]\nd = 1\n\n[y]\nc = 1\n"
+    )]
+    #[case::inserted(
+        "[x]\n# Comment\na = 1\n\n[x.sub]\nb = 1\n\n[y]\nc = 1\n",
+        "[x]\na = 1\n\n[x.sub]\nb = 1\n\n[y]\nc = 1\n",
+        "[y]\nc = 1\n\n[x]\na = 1\n\n[x.sub]\nb = 1\n\n[x.new]\nd = 1\n",
+        "[x]\n# Comment\na = 1\n\n[x.sub]\nb = 1\n\n[x.new]\nd = 1\n\n[y]\nc = 1\n"
+    )]
+    fn patch_preserves_document(
+        #[case] document: &str,
+        #[case] old: &str,
+        #[case] new: &str,
+        #[case] expected: &str,
+    ) {
+        assert_eq!(patch(document, old, new).as_deref(), Some(expected));
+    }
+}
@@ -19,10 +19,29 @@ impl Error {
         }
     }
 
+    /// Create a new error from it's kind, concerning the whole repository rather than an [`Entry`].
+    pub fn head(inner: impl Into<Kind>) -> Self {
+        Self {
+            path: "HEAD",
+            inner: inner.into(),
+        }
+    }
+
     /// Access the `kind` of this error.
     pub fn kind(&self) -> &Kind {
         &self.inner
     }
+
+    /// Whether the error is caused by a missing [`Entry`] or repository `HEAD`,
+    /// meaning that the [`Entry`] is yet to be initialized.
+    pub fn is_missing(&self) -> bool {
+        matches!(
+            &self.inner,
+            Kind::Git(err)
+                if err.code() == git2::ErrorCode::UnbornBranch
+                    || err.code() == git2::ErrorCode::NotFound
+        )
+    }
 }
 
 /// The kind of [`struct@Error`]s that can occur while manipulating an [`Entry`].
@@ -40,7 +59,43 @@ pub enum Kind {
     #[error(transparent)]
     ConfigDe(#[from] toml::de::Error),
 
+    /// A _config parsing_ error, located in the document.
+    #[error(transparent)]
+    Diagnostic(#[from] super::Diagnostic),
+
+    /// A _config document_ error.
+    #[error(transparent)]
+    ConfigDoc(#[from] toml_edit::TomlError),
+
+    /// The document is from an unsupported version of the schema.
+    #[error(
+        "Unsupported schema version {version}, this server supports versions 1 to {supported}"
+    )]
+    UnsupportedVersion {
+        /// The version of the document.
+        version: u32,
+
+        /// The latest supported version.
+        supported: u32,
+    },
+
     /// An _UTF-8_ error.
     #[error(transparent)]
     Utf8(#[from] std::str::Utf8Error),
+
+    /// An _I/O_ error.
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    /// The document of the entry doesn't exist.
+    #[error("The entry is missing")]
+    Missing,
+
+    /// The repository is locked by another writer.
+    #[error("Timed out waiting for the repository lock, held by another writer")]
+    Locked,
+
+    /// The `HEAD` has been moved since the transaction started.
+    #[error("The repository has been modified concurrently, please retry")]
+    Conflict,
 }
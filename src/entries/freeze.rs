@@ -0,0 +1,323 @@
+use chrono::{DateTime, Datelike, NaiveDate, Timelike};
+use serde::{Deserialize, Serialize};
+use serde_with::{DeserializeFromStr, SerializeDisplay};
+use toml::value::{Datetime, Offset};
+
+use super::Owner;
+
+/// A time window during which updates to references are denied,
+/// either between two absolute dates or recurring weekly.
+///
+/// All times without an explicit offset are interpreted as UTC.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "RawFreeze", into = "RawFreeze")]
+pub struct Freeze {
+    window: Window,
+
+    /// A reason displayed to the pusher when the freeze is active.
+    pub reason: Option<String>,
+
+    /// The keys allowed to push anyway when the freeze is active.
+    pub break_glass: Vec<Owner>,
+}
+
+impl Freeze {
+    /// Whether the freeze is active at the `now` UNIX timestamp, in seconds.
+    pub fn is_active(&self, now: i64) -> bool {
+        match &self.window {
+            Window::Absolute { from, until } => (*from..*until).contains(&now),
+            Window::Weekly { from, until } => {
+                let now = Weekly::from_timestamp(now);
+
+                if from <= until {
+                    (from..until).contains(&&now)
+                } else {
+                    now >= *from || now < *until
+                }
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for Freeze {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let RawFreeze { from, until, .. } = self.clone().into();
+        write!(f, "from `{from}` until `{until}`")?;
+
+        if let Some(reason) = &self.reason {
+            write!(f, ": {reason}")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Window {
+    Absolute { from: i64, until: i64 },
+    Weekly { from: Weekly, until: Weekly },
+}
+
+/// The serialized representation of a [`Freeze`].
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+struct RawFreeze {
+    from: Moment,
+    until: Moment,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    reason: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    break_glass: Vec<Owner>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum Moment {
+    Absolute(Datetime),
+    Weekly(Weekly),
+}
+
+impl std::fmt::Display for Moment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Absolute(datetime) => write!(f, "{datetime}"),
+            Self::Weekly(weekly) => write!(f, "{weekly}"),
+        }
+    }
+}
+
+impl TryFrom<RawFreeze> for Freeze {
+    type Error = String;
+
+    fn try_from(value: RawFreeze) -> Result<Self, Self::Error> {
+        let window = match (value.from, value.until) {
+            (Moment::Absolute(from), Moment::Absolute(until)) => {
+                let (from, until) = (timestamp(&from)?, timestamp(&until)?);
+                if until <= from {
+                    Err("`until` must be after `from`, or the freeze would never be active")?;
+                }
+
+                Window::Absolute { from, until }
+            }
+            (Moment::Weekly(from), Moment::Weekly(until)) => {
+                if until == from {
+                    Err("`until` must differ from `from`, or the freeze would never be active")?;
+                }
+
+                Window::Weekly { from, until }
+            }
+            _ => Err("`from` and `until` must either both be dates or both be weekly times")?,
+        };
+
+        Ok(Self {
+            window,
+            reason: value.reason,
+            break_glass: value.break_glass,
+        })
+    }
+}
+
+impl From<Freeze> for RawFreeze {
+    fn from(value: Freeze) -> Self {
+        let (from, until) = match value.window {
+            Window::Absolute { from, until } => (
+                Moment::Absolute(datetime(from)),
+                Moment::Absolute(datetime(until)),
+            ),
+            Window::Weekly { from, until } => (Moment::Weekly(from), Moment::Weekly(until)),
+        };
+
+        Self {
+            from,
+            until,
+            reason: value.reason,
+            break_glass: value.break_glass,
+        }
+    }
+}
+
+/// A recurring moment of the week, formatted as `<weekday> <HH>:<MM>`, such as `fri 16:00`.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, DeserializeFromStr, SerializeDisplay,
+)]
+struct Weekly {
+    /// Minutes since monday, `00:00`.
+    minutes: u32,
+}
+
+impl Weekly {
+    const WEEKDAYS: [&'static str; 7] = ["mon", "tue", "wed", "thu", "fri", "sat", "sun"];
+    const NAMES: [&'static str; 7] = [
+        "monday",
+        "tuesday",
+        "wednesday",
+        "thursday",
+        "friday",
+        "saturday",
+        "sunday",
+    ];
+
+    fn from_timestamp(timestamp: i64) -> Self {
+        // The UNIX epoch was a thursday, the 4th day of the week.
+        let minutes = (timestamp.div_euclid(60) + 3 * 24 * 60).rem_euclid(7 * 24 * 60);
+
+        Self {
+            minutes: minutes as u32,
+        }
+    }
+}
+
+impl std::str::FromStr for Weekly {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || format!("`{s}` is not a valid weekly time, expected `<weekday> <HH>:<MM>`");
+
+        let (day, time) = s.trim().split_once(' ').ok_or_else(err)?;
+        let (hours, minutes) = time.trim().split_once(':').ok_or_else(err)?;
+
+        let day = day.to_ascii_lowercase();
+        let day = Self::WEEKDAYS
+            .iter()
+            .zip(Self::NAMES)
+            .position(|(weekday, name)| day == *weekday || day == name)
+            .ok_or_else(err)? as u32;
+        let hours = hours
+            .parse::<u32>()
+            .ok()
+            .filter(|h| *h < 24)
+            .ok_or_else(err)?;
+        let minutes = minutes
+            .parse::<u32>()
+            .ok()
+            .filter(|m| *m < 60)
+            .ok_or_else(err)?;
+
+        Ok(Self {
+            minutes: (day * 24 + hours) * 60 + minutes,
+        })
+    }
+}
+
+impl std::fmt::Display for Weekly {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {:02}:{:02}",
+            Self::WEEKDAYS[(self.minutes / (24 * 60)) as usize],
+            self.minutes / 60 % 24,
+            self.minutes % 60
+        )
+    }
+}
+
+/// Convert a TOML [`Datetime`] to a UNIX timestamp, in seconds.
+fn timestamp(datetime: &Datetime) -> Result<i64, String> {
+    let date = datetime
+        .date
+        .ok_or_else(|| format!("`{datetime}` is missing a date"))?;
+    let (hour, minute, second) = datetime
+        .time
+        .map(|time| (time.hour, time.minute, time.second))
+        .unwrap_or_default();
+    let offset = match datetime.offset {
+        Some(Offset::Custom { minutes }) => minutes as i64 * 60,
+        Some(Offset::Z) | None => 0,
+    };
+
+    let datetime = NaiveDate::from_ymd_opt(date.year.into(), date.month.into(), date.day.into())
+        .and_then(|date| date.and_hms_opt(hour.into(), minute.into(), second.into()))
+        .ok_or_else(|| format!("`{datetime}` is not a valid date"))?;
+
+    Ok(datetime.and_utc().timestamp() - offset)
+}
+
+/// Convert a UNIX timestamp, in seconds, to a TOML [`Datetime`] in UTC.
+fn datetime(timestamp: i64) -> Datetime {
+    let datetime = DateTime::from_timestamp(timestamp, 0)
+        .expect("The timestamp was computed from a valid date");
+
+    Datetime {
+        date: Some(toml::value::Date {
+            year: datetime.year() as u16,
+            month: datetime.month() as u8,
+            day: datetime.day() as u8,
+        }),
+        time: Some(toml::value::Time {
+            hour: datetime.hour() as u8,
+            minute: datetime.minute() as u8,
+            second: datetime.second() as u8,
+            nanosecond: 0,
+        }),
+        offset: Some(Offset::Z),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+
+    #[derive(Deserialize)]
+    struct Wrapper {
+        freeze: Freeze,
+    }
+
+    fn freeze(from: &str, until: &str) -> Freeze {
+        toml::from_str::<Wrapper>(&format!("freeze = {{ from = {from}, until = {until} }}"))
+            .expect("The freeze was malformed")
+            .freeze
+    }
+
+    #[rstest]
+    // 2024-12-24T12:00:00Z
+    #[case("2024-12-20T00:00:00Z", "2025-01-02", 1735041600, true)]
+    // 2025-01-02T00:00:00Z, the end is exclusive
+    #[case("2024-12-20T00:00:00Z", "2025-01-02", 1735776000, false)]
+    // 2024-12-19T23:30:00Z, with an offset of +01:00
+    #[case("2024-12-20T00:00:00+01:00", "2025-01-02", 1734651000, true)]
+    // Friday 2024-11-22T17:00:00Z
+    #[case("\"fri 16:00\"", "\"mon 08:00\"", 1732294800, true)]
+    // Sunday 2024-11-24T12:00:00Z
+    #[case("\"fri 16:00\"", "\"mon 08:00\"", 1732449600, true)]
+    // Monday 2024-11-25T09:00:00Z
+    #[case("\"fri 16:00\"", "\"mon 08:00\"", 1732525200, false)]
+    // Wednesday 2024-11-20T12:00:00Z
+    #[case("\"fri 16:00\"", "\"mon 08:00\"", 1732104000, false)]
+    // Wednesday 2024-11-20T12:00:00Z
+    #[case("\"Tuesday 09:30\"", "\"thu 00:00\"", 1732104000, true)]
+    fn it_computes_activity(
+        #[case] from: &str,
+        #[case] until: &str,
+        #[case] now: i64,
+        #[case] expected: bool,
+    ) {
+        assert_eq!(freeze(from, until).is_active(now), expected);
+    }
+
+    #[rstest]
+    #[case(0)]
+    #[case(1735041600)]
+    #[case(-86401)]
+    fn it_roundtrips_timestamps(#[case] now: i64) {
+        assert_eq!(timestamp(&datetime(now)), Ok(now));
+    }
+
+    #[rstest]
+    #[case("\"fri 16:00\"", "2025-01-02")]
+    #[case("\"fri 24:00\"", "\"mon 08:00\"")]
+    #[case("\"someday 16:00\"", "\"mon 08:00\"")]
+    #[case("\"monkey 16:00\"", "\"tue 08:00\"")]
+    #[case("12:00:00", "13:00:00")]
+    #[case("2025-01-02", "2024-12-20T00:00:00Z")]
+    #[case("2025-01-02T00:00:00Z", "2025-01-02T01:00:00+01:00")]
+    #[case("2025-02-30", "2025-03-02")]
+    #[case("\"fri 16:00\"", "\"friday 16:00\"")]
+    fn it_denies_malformed_windows(#[case] from: &str, #[case] until: &str) {
+        let _ =
+            toml::from_str::<Wrapper>(&format!("freeze = {{ from = {from}, until = {until} }}"))
+                .err()
+                .expect("The freeze was malformed, but didn't error");
+    }
+}
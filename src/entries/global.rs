@@ -1,6 +1,8 @@
+use std::path::PathBuf;
+
 use serde::{Deserialize, Serialize};
 
-use super::Entry;
+use super::{Email, Entry};
 
 impl Entry<()> for Global {
     const PATH: &'static str = "Global.toml";
@@ -10,9 +12,18 @@ impl Entry<()> for Global {
 #[derive(Debug, Default, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct Global {
+    #[serde(default, skip_serializing, rename = "version")]
+    _version: serde::de::IgnoredAny,
+
     /// Server's _self-registration_ policy.
     #[serde(default)]
     pub registration: RegistrationPolicy,
+
+    /// Server's _outgoing mail_ configuration, disabling emails if unset.
+    pub mail: Option<Mail>,
+
+    /// Server's _Git LFS_ configuration, using the defaults if unset.
+    pub lfs: Option<Lfs>,
 }
 
 impl From<()> for Global {
@@ -32,3 +43,35 @@ pub enum RegistrationPolicy {
     #[default]
     Deny,
 }
+
+/// Server's _outgoing mail_ configuration, used to send notifications.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Mail {
+    /// The address the emails are sent from.
+    pub from: Email,
+
+    /// The `sendmail`-compatible binary used to send the emails,
+    /// defaults to `sendmail` in the `PATH`.
+    pub sendmail: Option<PathBuf>,
+
+    /// The `host:port` of an SMTP relay used to send the emails,
+    /// which takes precedence over `sendmail` if set.
+    pub relay: Option<String>,
+}
+
+/// Server's _Git LFS_ configuration, limiting the stored objects.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Lfs {
+    /// The maximum size of the objects uploaded by the clients, in bytes.
+    pub max_size: u64,
+}
+
+impl Default for Lfs {
+    fn default() -> Self {
+        Self {
+            max_size: 1024 * 1024 * 1024,
+        }
+    }
+}
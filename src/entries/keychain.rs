@@ -1,6 +1,12 @@
+use std::collections::{BTreeMap, HashMap};
+
 use nonempty::{nonempty, NonEmpty};
+use parse_display::Display;
 use serde::{Deserialize, Serialize};
-use ssh_key::PublicKey;
+use serde_with::{
+    serde_as, DeserializeFromStr, DisplayFromStr, MapPreventDuplicates, SerializeDisplay,
+};
+use ssh_key::{Fingerprint, PublicKey};
 
 use super::Entry;
 
@@ -9,10 +15,26 @@ impl Entry<&PublicKey> for Keychain {
 }
 
 /// An [`Entry`] describing _keys_ for the namespace.
+#[serde_as]
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct Keychain {
+    #[serde(default, skip_serializing, rename = "version")]
+    _version: serde::de::IgnoredAny,
+
     keys: NonEmpty<PublicKey>,
+
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    #[serde_as(as = "MapPreventDuplicates<_, _>")]
+    groups: HashMap<String, Vec<PublicKey>>,
+
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    #[serde_as(as = "MapPreventDuplicates<DisplayFromStr, _>")]
+    emails: BTreeMap<Fingerprint, Email>,
+
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    #[serde_as(as = "MapPreventDuplicates<_, _>")]
+    roles: HashMap<Role, Vec<Owner>>,
 }
 
 impl Keychain {
@@ -24,12 +46,197 @@ impl Keychain {
             .iter()
             .any(|k| k.fingerprint(Default::default()) == fingerprint)
     }
+
+    /// Compute whether the provided `key` is designated by the [`Owner`].
+    pub fn owns(&self, owner: &Owner, key: &PublicKey) -> bool {
+        let fingerprint = key.fingerprint(Default::default());
+
+        match owner {
+            Owner::Group(name) => self.groups.get(name).is_some_and(|keys| {
+                keys.iter()
+                    .any(|k| k.fingerprint(Default::default()) == fingerprint)
+            }),
+            Owner::Key(owner) => *owner == fingerprint,
+        }
+    }
+
+    /// Compute whether the provided `key` has the [`Role`],
+    /// which is always the case for the keys of the [`Keychain`].
+    pub fn has_role(&self, role: Role, key: &PublicKey) -> bool {
+        self.contains(key)
+            || self
+                .roles
+                .get(&role)
+                .is_some_and(|owners| owners.iter().any(|owner| self.owns(owner, key)))
+    }
+
+    /// Add the provided `key` to the [`Keychain`], returning whether it was not already present.
+    pub fn add(&mut self, key: PublicKey) -> bool {
+        if self.contains(&key) {
+            return false;
+        }
+
+        self.keys.push(key);
+
+        true
+    }
+
+    /// Remove the key with the provided `fingerprint` from the [`Keychain`],
+    /// returning whether it was removed, which is never the case for the last key.
+    pub fn remove(&mut self, fingerprint: &Fingerprint) -> bool {
+        let keys = self
+            .keys
+            .iter()
+            .filter(|key| key.fingerprint(fingerprint.algorithm()) != *fingerprint)
+            .cloned()
+            .collect::<Vec<_>>();
+
+        match NonEmpty::from_vec(keys) {
+            Some(keys) if keys.len() < self.keys.len() => {
+                self.keys = keys;
+
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Find the email address associated with the provided `key`, if any.
+    pub fn email(&self, key: &PublicKey) -> Option<&str> {
+        self.emails
+            .get(&key.fingerprint(Default::default()))
+            .map(Email::as_str)
+    }
+
+    /// List the email addresses of the keys designated by the [`Owner`].
+    pub fn emails(&self, owner: &Owner) -> Vec<&str> {
+        match owner {
+            Owner::Group(name) => self
+                .groups
+                .get(name)
+                .into_iter()
+                .flatten()
+                .filter_map(|key| self.email(key))
+                .collect(),
+            Owner::Key(fingerprint) => self
+                .emails
+                .get(fingerprint)
+                .map(Email::as_str)
+                .into_iter()
+                .collect(),
+        }
+    }
 }
 
 impl From<&PublicKey> for Keychain {
     fn from(value: &PublicKey) -> Self {
         Self {
+            _version: Default::default(),
             keys: nonempty![value.clone()],
+            groups: Default::default(),
+            emails: Default::default(),
+            roles: Default::default(),
         }
     }
 }
+
+/// A restricted set of permissions which may be granted to keys outside of the [`Keychain`].
+#[derive(Debug, Display, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[display(style = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// Report commit statuses on the repositories of the namespace.
+    Status,
+
+    /// Submit proposals to the repositories of the namespace, by pushing to `refs/for/<branch>`.
+    Propose,
+}
+
+/// A designation of one or more keys, either as a `@group`
+/// of the [`Keychain`], or as a single key `SHA256:` fingerprint.
+#[derive(Debug, Clone, PartialEq, Eq, DeserializeFromStr, SerializeDisplay)]
+pub enum Owner {
+    /// A named group of keys from the [`Keychain`].
+    Group(String),
+
+    /// A single key, designated by it's fingerprint.
+    Key(Fingerprint),
+}
+
+impl std::str::FromStr for Owner {
+    type Err = ssh_key::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.strip_prefix('@') {
+            Some(group) => Ok(Self::Group(group.into())),
+            None => s.parse().map(Self::Key),
+        }
+    }
+}
+
+impl std::fmt::Display for Owner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Group(name) => write!(f, "@{name}"),
+            Self::Key(fingerprint) => write!(f, "{fingerprint}"),
+        }
+    }
+}
+
+/// An email address, restricted to a single `@` and no whitespace, control characters
+/// nor angle brackets, to be safely interpolated in mail headers and SMTP commands,
+/// nor a leading `-`, not to be mistaken for an option by `sendmail`.
+#[derive(Debug, Clone, PartialEq, Eq, DeserializeFromStr, SerializeDisplay)]
+pub struct Email(String);
+
+impl Email {
+    /// Access the address as a string slice.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::str::FromStr for Email {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let valid = s.split_once('@').is_some_and(|(local, domain)| {
+            !local.is_empty() && !domain.is_empty() && !domain.contains('@')
+        }) && !s.starts_with('-')
+            && !s.contains(|c: char| c.is_whitespace() || c.is_control() || c == '<' || c == '>');
+
+        if valid {
+            Ok(Self(s.into()))
+        } else {
+            Err(format!(
+                "`{}` is not a valid email address",
+                s.escape_debug()
+            ))
+        }
+    }
+}
+
+impl std::fmt::Display for Email {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+
+    #[rstest]
+    #[case("git@example.com", true)]
+    #[case("first.last+tag@sub.example.com", true)]
+    #[case("example.com", false)]
+    #[case("a@b@example.com", false)]
+    #[case("git@example.com>\r\nRCPT TO:<victim@example.com", false)]
+    #[case("git@example.com\nBcc: victim@example.com", false)]
+    #[case("-oQ/tmp/@example.com", false)]
+    fn it_validates_emails(#[case] email: &str, #[case] valid: bool) {
+        assert_eq!(email.parse::<Email>().is_ok(), valid);
+    }
+}
@@ -2,7 +2,7 @@
 
 use std::path::Path;
 
-use git2::{build::TreeUpdateBuilder, FileMode, Oid};
+use git2::Oid;
 use serde::{de::DeserializeOwned, Serialize};
 
 use super::Repository;
@@ -10,14 +10,41 @@ use super::Repository;
 mod error;
 pub use error::{Error, Kind as ErrorKind};
 
+mod diagnostic;
+pub use diagnostic::Diagnostic;
+
+mod document;
+
+mod transaction;
+pub(crate) use transaction::Lock;
+pub use transaction::Transaction;
+
+mod version;
+pub use version::Migration;
+
 mod global;
-pub use global::{Global, RegistrationPolicy};
+pub use global::{Global, Lfs, Mail, RegistrationPolicy};
 
 mod keychain;
-pub use keychain::Keychain;
+pub use keychain::{Email, Keychain, Owner, Role};
 
 mod repositories;
-pub use repositories::{RefConfig, Repositories, Visibility};
+pub use repositories::{
+    ArchiveConfig, Job, Mirror, Notifications, PathRule, Recipient, RefConfig, Repositories,
+    Secrets, SecretsPolicy, Spec, Upstream, Visibility, Webhook, WebhookEvent,
+};
+
+mod pattern;
+pub use pattern::Pattern;
+
+mod freeze;
+pub use freeze::Freeze;
+
+mod remote;
+pub use remote::Remote;
+
+mod url;
+pub use url::HttpUrl;
 
 /// The trait representing an [`Entry`],
 /// which allows R/W operations on a repository storing those kind of informations.
@@ -25,6 +52,12 @@ pub trait Entry<Args>: Serialize + DeserializeOwned + From<Args> {
     /// The in-repository path for this [`Entry`].
     const PATH: &'static str;
 
+    /// The migrations of the [`Entry`]'s document, the first one upgrading it from the version `1`.
+    const MIGRATIONS: &'static [Migration] = &[];
+
+    /// The current version of the [`Entry`]'s schema, written as the `version` key of it's document.
+    const VERSION: u32 = Self::MIGRATIONS.len() as u32 + 1;
+
     /// Load the [`Entry`] from the repository's `HEAD`.
     fn load(repository: &Repository) -> Result<Self, Error> {
         let head = (|| Ok(repository.head()?.peel_to_commit()?))()
@@ -43,71 +76,57 @@ pub trait Entry<Args>: Serialize + DeserializeOwned + From<Args> {
                 .to_object(repository)?
                 .peel_to_blob()?;
 
-            let content = std::str::from_utf8(blob.content())?;
-
-            Ok(toml::from_str(content)?)
+            Ok(std::str::from_utf8(blob.content())?.to_owned())
         })()
         .map_err(|err: ErrorKind| Error::new::<Args, Self>(err))
+        .and_then(|content| Self::parse(&content))
+    }
+
+    /// Parse the [`Entry`] from the `content` of it's document, upgrading it if it's from an older version.
+    fn parse(content: &str) -> Result<Self, Error> {
+        version::parse::<Args, Self>(content).map_err(Error::new::<Args, Self>)
     }
 
     /// Load the [`Entry`] from the repository's `HEAD`, or initialize it from the provided `args`.
     fn load_or_init(repository: &Repository, args: Args) -> Result<Self, Error> {
-        Self::load(repository).or_else(|err| {
-            // Initialize the entry only if it is not found
-            match err.kind() {
-                ErrorKind::Git(err)
-                    if err.code() == git2::ErrorCode::UnbornBranch
-                        || err.code() == git2::ErrorCode::NotFound =>
-                {
-                    let config = Self::from(args);
-
-                    config
-                        .commit(
-                            repository,
-                            &format!("Initialization of the `{}` configuration file", Self::PATH),
-                        )
-                        .map(|_| config)
-                }
-                _ => Err(err),
-            }
-        })
+        // Only lock the repository when the entry is to be initialized.
+        match Self::load(repository) {
+            Err(err) if err.is_missing() => (),
+            other => return other,
+        }
+
+        let mut transaction = Transaction::begin(repository)?;
+        let entry = transaction.load_or_init(args)?;
+
+        transaction.commit(
+            &format!("Initialization of the `{}` configuration file", Self::PATH),
+            &signature().map_err(Error::head)?,
+        )?;
+
+        Ok(entry)
     }
 
     /// Commit the [`Entry`] to the repository with a custom commit `message`.
     fn commit(&self, repository: &Repository, message: &str) -> Result<(), Error> {
-        (|| {
-            let blob = repository.blob(toml::to_string_pretty(&self)?.as_bytes())?;
-            let signature = git2::Signature::now("furrow", "git@server.commit")?;
-
-            if let Some(parent) = repository
-                .head()
-                .ok()
-                .map(|reference| reference.peel_to_commit())
-                .transpose()?
-            {
-                let tree = TreeUpdateBuilder::new()
-                    .upsert(Self::PATH, blob, FileMode::Blob)
-                    .create_updated(repository, &parent.tree()?)?;
-                let tree = repository.find_tree(tree)?;
-
-                repository.commit(
-                    Some("HEAD"),
-                    &signature,
-                    &signature,
-                    message,
-                    &tree,
-                    &[&parent],
-                )?;
-            } else {
-                let mut treebuilder = repository.treebuilder(None)?;
-                treebuilder.insert(Self::PATH, blob, FileMode::Blob.into())?;
-                let tree = repository.find_tree(treebuilder.write()?)?;
-
-                repository.commit(Some("HEAD"), &signature, &signature, message, &tree, &[])?;
-            }
-
-            Ok(())
-        })()
-        .map_err(|err: ErrorKind| Error::new::<Args, Self>(err))
+        self.commit_as(repository, message, &signature().map_err(Error::head)?)
+    }
+
+    /// Commit the [`Entry`] to the repository with a custom commit `message`,
+    /// recording the `author` of the modification.
+    fn commit_as(
+        &self,
+        repository: &Repository,
+        message: &str,
+        author: &git2::Signature,
+    ) -> Result<(), Error> {
+        let mut transaction = Transaction::begin(repository)?;
+        transaction.stage(self)?;
+
+        transaction.commit(message, author)
     }
 }
+
+/// The signature of the server, used for the modifications it makes by itself.
+pub(crate) fn signature() -> Result<git2::Signature<'static>, git2::Error> {
+    git2::Signature::now("furrow", "git@server.commit")
+}
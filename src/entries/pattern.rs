@@ -0,0 +1,113 @@
+use std::hash::{Hash, Hasher};
+
+use regex::Regex;
+use serde_with::{DeserializeFromStr, SerializeDisplay};
+
+/// A _glob_ pattern matched against slash-separated paths,
+/// following the usual `.gitignore` semantics.
+///
+/// - `*` matches anything but a `/`, `?` matches a single character but a `/`.
+/// - `**` matches anything, including `/`.
+/// - A pattern without a `/` matches at any depth, otherwise it is anchored at the root.
+/// - A pattern matching a directory also matches everything below it.
+#[derive(Debug, Clone, DeserializeFromStr, SerializeDisplay)]
+pub struct Pattern {
+    source: String,
+    regex: Regex,
+}
+
+impl Pattern {
+    /// Whether the `path` matches this [`Pattern`].
+    pub fn is_match(&self, path: &str) -> bool {
+        self.regex.is_match(path)
+    }
+}
+
+impl std::str::FromStr for Pattern {
+    type Err = regex::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let glob = s.strip_suffix('/').unwrap_or(s);
+        let (anchored, glob) = match glob.strip_prefix('/') {
+            Some(glob) => (true, glob),
+            None => (glob.contains('/'), glob),
+        };
+
+        let mut regex = String::from(if anchored { "^" } else { "^(?:.*/)?" });
+        let mut chars = glob.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '*' if chars.peek() == Some(&'*') => {
+                    chars.next();
+
+                    if chars.peek() == Some(&'/') {
+                        chars.next();
+                        regex.push_str("(?:.*/)?");
+                    } else {
+                        regex.push_str(".*");
+                    }
+                }
+                '*' => regex.push_str("[^/]*"),
+                '?' => regex.push_str("[^/]"),
+                c => regex.push_str(&regex::escape(c.encode_utf8(&mut [0; 4]))),
+            }
+        }
+
+        regex.push_str("(?:/.*)?$");
+
+        Ok(Self {
+            source: s.into(),
+            regex: Regex::new(&regex)?,
+        })
+    }
+}
+
+impl std::fmt::Display for Pattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+impl PartialEq for Pattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source
+    }
+}
+
+impl Eq for Pattern {}
+
+impl Hash for Pattern {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.source.hash(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+
+    #[rstest]
+    #[case("*.pem", "key.pem", true)]
+    #[case("*.pem", "deploy/keys/key.pem", true)]
+    #[case("*.pem", "key.pem.txt", false)]
+    #[case("deploy/**", "deploy/prod/main.tf", true)]
+    #[case("deploy/**", "src/deploy/main.tf", false)]
+    #[case("/deploy", "deploy/main.tf", true)]
+    #[case("docs/", "docs/index.md", true)]
+    #[case("docs", "crates/docs/index.md", true)]
+    #[case("src/*.rs", "src/lib.rs", true)]
+    #[case("src/*.rs", "src/id/mod.rs", false)]
+    #[case("src/**/*.rs", "src/lib.rs", true)]
+    #[case("src/**/*.rs", "src/id/mod.rs", true)]
+    #[case("fixture?.key", "tests/fixture1.key", true)]
+    #[case("a+b.txt", "a+b.txt", true)]
+    #[case("a+b.txt", "aab.txt", false)]
+    fn it_matches_paths(#[case] pattern: &str, #[case] path: &str, #[case] expected: bool) {
+        let pattern: Pattern = pattern.parse().expect(pattern);
+
+        assert_eq!(pattern.is_match(path), expected, "{pattern} ~ {path}");
+    }
+}
@@ -0,0 +1,76 @@
+use serde_with::{DeserializeFromStr, SerializeDisplay};
+
+/// The URL of a remote repository, restricted to the network transports of [`Remote::PROTOCOLS`].
+///
+/// Local paths, `file://` URLs and the `<transport>::<address>` syntax are refused,
+/// since they would give access to the server's own storage.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, DeserializeFromStr, SerializeDisplay)]
+pub struct Remote(String);
+
+impl Remote {
+    /// The protocols allowed for the remotes, as named by `git`'s `protocol.<name>.allow`.
+    pub const PROTOCOLS: [&'static str; 3] = ["ssh", "https", "git"];
+
+    /// Access the URL as a string slice.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::str::FromStr for Remote {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty()
+            || s.starts_with('-')
+            || s.contains(|c: char| c.is_whitespace() || c.is_control())
+        {
+            return Err(format!("`{s}` is not a valid remote URL"));
+        }
+
+        let allowed = match s.split_once("://") {
+            Some((scheme, _)) => Self::PROTOCOLS.contains(&scheme),
+            // The scp-like syntax `[user@]host:path`, with no slash before the colon.
+            None => s.split_once(':').is_some_and(|(host, _)| {
+                !host.is_empty() && !host.contains('/') && !s.contains("::")
+            }),
+        };
+
+        if !allowed {
+            return Err(format!(
+                "`{s}` is not an allowed remote URL, only the {} protocols are supported",
+                Self::PROTOCOLS.join(", ")
+            ));
+        }
+
+        Ok(Self(s.into()))
+    }
+}
+
+impl std::fmt::Display for Remote {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+
+    #[rstest]
+    #[case("https://example.com/repo.git", true)]
+    #[case("ssh://git@example.com/repo.git", true)]
+    #[case("git://example.com/repo.git", true)]
+    #[case("git@example.com:repo.git", true)]
+    #[case("/srv/furrow/other/private.git", false)]
+    #[case("../other/private.git", false)]
+    #[case("file:///srv/furrow/other/private.git", false)]
+    #[case("ext::sh -c touch% /tmp/pwned", false)]
+    #[case("fd::17", false)]
+    #[case("--upload-pack=touch /tmp/pwned", false)]
+    fn it_restricts_the_protocols(#[case] url: &str, #[case] valid: bool) {
+        assert_eq!(url.parse::<Remote>().is_ok(), valid);
+    }
+}
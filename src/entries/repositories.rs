@@ -1,9 +1,13 @@
-use std::{collections::HashMap, ops::Deref};
+use std::{
+    collections::HashMap,
+    ops::{Deref, DerefMut},
+};
 
+use parse_display::{Display, FromStr};
 use serde::{Deserialize, Serialize};
-use serde_with::{serde_as, MapPreventDuplicates};
+use serde_with::{serde_as, DeserializeFromStr, MapPreventDuplicates, SerializeDisplay};
 
-use super::Entry;
+use super::{Email, Entry, Freeze, HttpUrl, Owner, Pattern, Remote};
 use crate::id::Base;
 
 impl Entry<()> for Repositories {
@@ -15,9 +19,20 @@ impl Entry<()> for Repositories {
 #[derive(Debug, Default, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct Repositories {
+    #[serde(default, skip_serializing, rename = "version")]
+    _version: serde::de::IgnoredAny,
+
     #[serde(default)]
     #[serde_as(as = "MapPreventDuplicates<_, _>")]
     repositories: HashMap<Base, Spec>,
+
+    /// Webhooks notified of pushes to any repository of the namespace.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub webhooks: Vec<Webhook>,
+
+    /// Email notifications of pushes to any repository of the namespace.
+    #[serde(default, skip_serializing_if = "Notifications::is_default")]
+    pub notifications: Notifications,
 }
 
 impl From<()> for Repositories {
@@ -29,22 +44,92 @@ impl From<()> for Repositories {
 /// The configuration for a _repositories_, with some metadata
 /// and some technical configuration.
 #[serde_as]
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct Spec {
+    /// A short description of the repository.
     pub description: Option<String>,
+
+    /// The license of the repository's content, as an SPDX identifier.
     pub license: Option<String>,
 
+    /// Who is allowed to read and write the repository.
     #[serde(default)]
     pub visibility: Visibility,
+
+    /// The pattern the names of the branches must match, if restricted.
     #[serde_as(as = "Option<serde_with::DisplayFromStr>")]
     pub branches: Option<regex::Regex>,
+
+    /// The pattern the names of the tags must match, if restricted.
     #[serde_as(as = "Option<serde_with::DisplayFromStr>")]
     pub tags: Option<regex::Regex>,
 
-    #[serde(default)]
+    /// The configuration of the branches, by name.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
     #[serde_as(as = "MapPreventDuplicates<_, _>")]
     pub branch: HashMap<String, RefConfig>,
+
+    /// The configuration of the other references, by pattern.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[serde_as(as = "serde_with::Map<_, _>")]
+    pub refs: Vec<(Pattern, RefConfig)>,
+
+    /// The scanning of the pushed content for secrets.
+    #[serde(default, skip_serializing_if = "Secrets::is_default")]
+    pub secrets: Secrets,
+
+    /// The owners of the paths of the repository.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub paths: Vec<PathRule>,
+
+    /// The time windows during which pushes to the repository are denied.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub freeze: Vec<Freeze>,
+
+    /// The webhooks notified of pushes to the repository.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub webhooks: Vec<Webhook>,
+
+    /// The remotes the repository is mirrored to.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub mirrors: Vec<Mirror>,
+
+    /// The remote the repository is tracking, making it read-only.
+    pub upstream: Option<Upstream>,
+
+    /// The email notifications of pushes to the repository.
+    #[serde(default, skip_serializing_if = "Notifications::is_default")]
+    pub notifications: Notifications,
+
+    /// The jobs ran on the pushed commits.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub jobs: Vec<Job>,
+
+    /// The restrictions on the archives generated with `git archive --remote`.
+    #[serde(default, skip_serializing_if = "ArchiveConfig::is_default")]
+    pub archive: ArchiveConfig,
+}
+
+impl Spec {
+    /// Find the [`RefConfig`] of a reference outside of the `refs/heads/` and `refs/tags/` namespaces,
+    /// from the last of the patterns matching the full `refname`, if any.
+    pub fn refconfig(&self, refname: &str) -> Option<&RefConfig> {
+        self.refs
+            .iter()
+            .rev()
+            .find(|(pattern, _)| pattern.is_match(refname))
+            .map(|(_, config)| config)
+    }
+}
+
+impl Repositories {
+    /// List the repositories defined in the [`Repositories`], but not in the `other` ones.
+    pub fn removed<'s>(&'s self, other: &'s Self) -> impl Iterator<Item = &'s Base> {
+        self.repositories
+            .keys()
+            .filter(|name| !other.repositories.contains_key(*name))
+    }
 }
 
 impl Deref for Repositories {
@@ -55,8 +140,15 @@ impl Deref for Repositories {
     }
 }
 
+impl DerefMut for Repositories {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.repositories
+    }
+}
+
 /// Repository's visibility configuration.
-#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[derive(Debug, Display, FromStr, Default, Clone, Serialize, Deserialize)]
+#[display(style = "lowercase")]
 #[serde(rename_all = "lowercase")]
 pub enum Visibility {
     /// Only repo owner can clone this repository.
@@ -79,6 +171,14 @@ pub struct RefConfig {
 
     /// Whether _deletes_ are allowed for this `ref`.
     pub allow_delete: bool,
+
+    /// The time windows during which updates to this `ref` are denied.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub freeze: Vec<Freeze>,
+
+    /// The status contexts required to be successful on the new tip of this `ref`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub require_statuses: Vec<String>,
 }
 
 impl RefConfig {
@@ -87,6 +187,8 @@ impl RefConfig {
         Self {
             allow_force: false,
             allow_delete: false,
+            freeze: Vec::new(),
+            require_statuses: Vec::new(),
         }
     }
 
@@ -95,6 +197,8 @@ impl RefConfig {
         Self {
             allow_force: true,
             allow_delete: true,
+            freeze: Vec::new(),
+            require_statuses: Vec::new(),
         }
     }
 }
@@ -104,3 +208,296 @@ impl Default for RefConfig {
         Self::unprotected()
     }
 }
+
+/// A rule restricting the modification of the paths matching the `pattern`
+/// to the keys designated by the `owners`.
+///
+/// When multiple rules match a path, the last one takes precedence.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PathRule {
+    /// The pattern of the paths this rule applies to.
+    pub pattern: Pattern,
+
+    /// The keys allowed to modify the paths, none if empty.
+    #[serde(default)]
+    pub owners: Vec<Owner>,
+}
+
+/// Repository's _secret scanning_ configuration, applied to pushed content.
+#[serde_as]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Secrets {
+    /// The action to take when a secret is found in pushed content.
+    #[serde(default)]
+    pub policy: SecretsPolicy,
+
+    /// Additionnal patterns to look for, on top of the built-in ones.
+    #[serde(default)]
+    #[serde_as(as = "Vec<serde_with::DisplayFromStr>")]
+    pub patterns: Vec<regex::Regex>,
+}
+
+impl Secrets {
+    /// Whether the configuration is the default one, scanning with the built-in patterns only.
+    pub fn is_default(&self) -> bool {
+        self.policy == SecretsPolicy::default() && self.patterns.is_empty()
+    }
+}
+
+/// The action to take when a secret is found in pushed content.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SecretsPolicy {
+    /// Reject the push altogether.
+    #[default]
+    Reject,
+
+    /// Accept the push, but warn the pusher about the findings.
+    Warn,
+
+    /// Do not scan the pushed content.
+    Ignore,
+}
+
+/// Repository's _remote archives_ configuration, for `git archive --remote`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ArchiveConfig {
+    /// The archive formats the clients may request.
+    #[serde(default = "ArchiveConfig::default_formats")]
+    pub formats: Vec<String>,
+
+    /// Whether the clients may archive any tree-ish, rather than only the references and their trees.
+    #[serde(default)]
+    pub allow_unreachable: bool,
+}
+
+impl ArchiveConfig {
+    fn default_formats() -> Vec<String> {
+        ["tar", "tgz", "tar.gz", "zip"].map(Into::into).to_vec()
+    }
+
+    /// Whether the configuration is the default one.
+    pub fn is_default(&self) -> bool {
+        self.formats == Self::default_formats() && !self.allow_unreachable
+    }
+
+    /// Whether the clients may request an archive in the `format`.
+    pub fn allows(&self, format: &str) -> bool {
+        self.formats.iter().any(|allowed| allowed == format)
+    }
+}
+
+impl Default for ArchiveConfig {
+    fn default() -> Self {
+        Self {
+            formats: Self::default_formats(),
+            allow_unreachable: false,
+        }
+    }
+}
+
+/// A webhook, notified with a JSON payload of pushes to repositories.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Webhook {
+    /// The URL the payloads are `POST`ed to.
+    pub url: HttpUrl,
+
+    /// The secret the payloads are signed with, using HMAC-SHA256.
+    pub secret: Option<String>,
+
+    /// The events the webhook is notified of, all of them if empty.
+    #[serde(default)]
+    pub events: Vec<WebhookEvent>,
+}
+
+impl Webhook {
+    /// Whether the webhook is to be notified of the `event`.
+    pub fn accepts(&self, event: WebhookEvent) -> bool {
+        self.events.is_empty() || self.events.contains(&event)
+    }
+}
+
+/// An event a [`Webhook`] can be notified of.
+#[derive(Debug, Display, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[display(style = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum WebhookEvent {
+    /// A branch was created or updated.
+    Push,
+
+    /// A tag was created or updated.
+    Tag,
+
+    /// Any other reference was created or updated.
+    Ref,
+
+    /// A reference was deleted.
+    Delete,
+}
+
+/// A downstream remote the repository is mirrored to, after each push.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Mirror {
+    /// The URL of the remote, as understood by `git push`.
+    pub url: Remote,
+
+    /// The pattern of the references to mirror, all of them if unset.
+    pub refs: Option<Pattern>,
+
+    /// The name of the SSH private key used to authenticate with the remote,
+    /// in the server's credentials directory.
+    pub credentials: Option<Base>,
+}
+
+/// A CI job, running commands on a checkout of the pushed commits.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Job {
+    /// The name of the job, unique in the repository.
+    pub name: String,
+
+    /// The pattern of the references triggering the job, all the branches if unset.
+    pub refs: Option<Pattern>,
+
+    /// The commands run in sequence with `sh -c`, stopping at the first failure.
+    pub run: Vec<String>,
+
+    /// The maximum duration of the job, in seconds.
+    #[serde(default = "Job::default_timeout")]
+    pub timeout: u64,
+}
+
+impl Job {
+    fn default_timeout() -> u64 {
+        3600
+    }
+}
+
+/// An upstream remote the repository is periodically fetched from,
+/// making it read-only for pushes.
+///
+/// It may be written either as a simple URL, or as a table with an `interval`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "RawUpstream", into = "RawUpstream")]
+pub struct Upstream {
+    /// The URL of the remote, as understood by `git fetch`.
+    pub url: Remote,
+
+    /// The interval between two fetches, in seconds.
+    pub interval: u64,
+}
+
+impl Upstream {
+    /// The default interval between two fetches, in seconds.
+    pub const DEFAULT_INTERVAL: u64 = 3600;
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum RawUpstream {
+    Url(String),
+    #[serde(rename_all = "kebab-case")]
+    Full {
+        url: String,
+        #[serde(default = "RawUpstream::default_interval")]
+        interval: u64,
+    },
+}
+
+impl RawUpstream {
+    fn default_interval() -> u64 {
+        Upstream::DEFAULT_INTERVAL
+    }
+}
+
+impl TryFrom<RawUpstream> for Upstream {
+    type Error = String;
+
+    fn try_from(value: RawUpstream) -> Result<Self, Self::Error> {
+        match value {
+            RawUpstream::Url(url) => Ok(Self {
+                url: url.parse()?,
+                interval: Self::DEFAULT_INTERVAL,
+            }),
+            RawUpstream::Full { url, interval } => Ok(Self {
+                url: url.parse()?,
+                interval,
+            }),
+        }
+    }
+}
+
+impl From<Upstream> for RawUpstream {
+    fn from(value: Upstream) -> Self {
+        match value {
+            Upstream { url, interval } if interval == Upstream::DEFAULT_INTERVAL => {
+                Self::Url(url.to_string())
+            }
+            Upstream { url, interval } => Self::Full {
+                url: url.to_string(),
+                interval,
+            },
+        }
+    }
+}
+
+/// The email notifications configuration, either for the whole namespace or a single repository.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Notifications {
+    /// Whether the notifications are sent, overriding the namespace's setting in a repository,
+    /// defaults to `true`.
+    pub enabled: Option<bool>,
+
+    /// The recipients of the notifications, added to the namespace's ones in a repository.
+    #[serde(default)]
+    pub recipients: Vec<Recipient>,
+}
+
+impl Notifications {
+    /// Whether the configuration is the default one, with no recipients.
+    pub fn is_default(&self) -> bool {
+        self.enabled.is_none() && self.recipients.is_empty()
+    }
+}
+
+/// A recipient of the email notifications, either as a raw email address,
+/// or as an [`Owner`] whose addresses are found in the keychain.
+#[derive(Debug, Clone, PartialEq, Eq, DeserializeFromStr, SerializeDisplay)]
+pub enum Recipient {
+    /// The keys designated by the [`Owner`].
+    Owner(Owner),
+
+    /// A raw email address.
+    Address(Email),
+}
+
+impl std::str::FromStr for Recipient {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.starts_with('@') || s.starts_with("SHA256:") {
+            s.parse()
+                .map(Self::Owner)
+                .map_err(|err| format!("`{s}` is not a valid owner: {err}"))
+        } else {
+            s.parse()
+                .map(Self::Address)
+                .map_err(|_| format!("`{s}` is neither an email address nor an owner"))
+        }
+    }
+}
+
+impl std::fmt::Display for Recipient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Owner(owner) => write!(f, "{owner}"),
+            Self::Address(address) => write!(f, "{address}"),
+        }
+    }
+}
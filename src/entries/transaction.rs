@@ -0,0 +1,348 @@
+use std::{
+    fs::{File, OpenOptions},
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use fs4::fs_std::FileExt;
+use git2::{build::TreeUpdateBuilder, Commit, ErrorCode, FileMode};
+
+use super::{document, version, Entry, Error, ErrorKind};
+use crate::Repository;
+
+/// The name of the lock file serializing the transactions, in the repository's directory.
+const LOCK_NAME: &str = "furrow.lock";
+
+/// The delay after which a transaction gives up waiting for the lock.
+const LOCK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A set of [`Entry`] modifications, committed at once on top of the `HEAD` it was started from.
+///
+/// The transactions hold a lock on the repository until they are committed or dropped,
+/// and the `HEAD` is only updated if it still points to the commit it was started from.
+pub struct Transaction<'r> {
+    repository: &'r Repository,
+    parent: Option<Commit<'r>>,
+    staged: Vec<(&'static str, String)>,
+    _lock: Lock,
+}
+
+impl<'r> Transaction<'r> {
+    /// Lock the `repository` and start a transaction from it's current `HEAD`,
+    /// blocking the thread while the lock is held by another writer.
+    pub fn begin(repository: &'r Repository) -> Result<Self, Error> {
+        let lock = Lock::acquire(&repository.path().join(LOCK_NAME)).map_err(Error::head)?;
+
+        let parent = match repository.head() {
+            Ok(head) => Some(head.peel_to_commit().map_err(Error::head)?),
+            Err(err)
+                if err.code() == ErrorCode::UnbornBranch || err.code() == ErrorCode::NotFound =>
+            {
+                None
+            }
+            Err(err) => return Err(Error::head(err)),
+        };
+
+        Ok(Self {
+            repository,
+            parent,
+            staged: Default::default(),
+            _lock: lock,
+        })
+    }
+
+    /// Load the [`Entry`] from the commit the transaction was started from.
+    pub fn load<A, T: Entry<A>>(&self) -> Result<T, Error> {
+        match &self.parent {
+            Some(parent) => T::load_at(self.repository, parent.id()),
+            None => Err(Error::new::<A, T>(git2::Error::new(
+                ErrorCode::UnbornBranch,
+                git2::ErrorClass::Reference,
+                "the repository has no commits yet",
+            ))),
+        }
+    }
+
+    /// Load the [`Entry`] from the commit the transaction was started from,
+    /// or stage it's initialization from the provided `args` if it doesn't exist.
+    pub fn load_or_init<A, T: Entry<A>>(&mut self, args: A) -> Result<T, Error> {
+        self.load().or_else(|err| {
+            if !err.is_missing() {
+                return Err(err);
+            }
+
+            let entry = T::from(args);
+            self.stage(&entry)?;
+
+            Ok(entry)
+        })
+    }
+
+    /// Stage the [`Entry`] to be committed, only modifying the changed keys of
+    /// it's existing document, if any, to preserve it's comments and ordering.
+    pub fn stage<A, T: Entry<A>>(&mut self, entry: &T) -> Result<(), Error> {
+        (|| {
+            let content = version::render(entry)?;
+
+            let content = self
+                .document::<A, T>()
+                .and_then(|document| {
+                    let document = match version::migrate::<A, T>(&document).ok()? {
+                        Some((_, migrated)) => migrated,
+                        None => document,
+                    };
+                    let previous: T = toml::from_str(&document).ok()?;
+
+                    document::patch(&document, &version::render(&previous).ok()?, &content)
+                })
+                .unwrap_or(content);
+
+            self.staged.retain(|(path, _)| *path != T::PATH);
+            self.staged.push((T::PATH, content));
+
+            Ok(())
+        })()
+        .map_err(|err: ErrorKind| Error::new::<A, T>(err))
+    }
+
+    /// Stage the migration of the [`Entry`]'s document to the current version of it's schema,
+    /// returning the version it's migrated from, or [`None`] if it's already up-to-date.
+    pub fn migrate<A, T: Entry<A>>(&mut self) -> Result<Option<u32>, Error> {
+        let Some(document) = self.document::<A, T>() else {
+            return Ok(None);
+        };
+
+        match version::migrate::<A, T>(&document).map_err(Error::new::<A, T>)? {
+            Some((version, migrated)) => {
+                self.staged.retain(|(path, _)| *path != T::PATH);
+                self.staged.push((T::PATH, migrated));
+
+                Ok(Some(version))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Read the [`Entry`]'s document from the commit the transaction was started from, if any.
+    fn document<A, T: Entry<A>>(&self) -> Option<String> {
+        let tree = self.parent.as_ref()?.tree().ok()?;
+
+        crate::authority::read_tree(self.repository, &tree, T::PATH)
+            .ok()
+            .flatten()
+    }
+
+    /// Read the document at `path` as it is to be committed,
+    /// from the staged entries or the commit the transaction was started from.
+    pub fn read(&self, path: &str) -> Result<Option<String>, ErrorKind> {
+        if let Some((_, content)) = self.staged.iter().find(|(staged, _)| *staged == path) {
+            return Ok(Some(content.clone()));
+        }
+
+        match &self.parent {
+            Some(parent) => crate::authority::read_tree(self.repository, &parent.tree()?, path),
+            None => Ok(None),
+        }
+    }
+
+    /// Commit the staged entries in a single commit with the `message`, recording the `author`
+    /// of the modifications, and release the lock. Nothing is committed if no entry was staged.
+    pub fn commit(self, message: &str, author: &git2::Signature) -> Result<(), Error> {
+        if self.staged.is_empty() {
+            return Ok(());
+        }
+
+        (|| {
+            let repository = self.repository;
+            let signature = super::signature()?;
+
+            let base = match &self.parent {
+                Some(parent) => parent.tree()?,
+                None => repository.find_tree(repository.treebuilder(None)?.write()?)?,
+            };
+
+            let mut builder = TreeUpdateBuilder::new();
+            for (path, content) in &self.staged {
+                builder.upsert(*path, repository.blob(content.as_bytes())?, FileMode::Blob);
+            }
+            let tree = repository.find_tree(builder.create_updated(repository, &base)?)?;
+
+            let parents: Vec<_> = self.parent.iter().collect();
+            let commit = repository.commit(None, author, &signature, message, &tree, &parents)?;
+
+            // Update the branch pointed by `HEAD` only if it didn't move since the transaction started.
+            let head = repository.find_reference("HEAD")?;
+            let branch = head
+                .symbolic_target()
+                .or(head.name())
+                .unwrap_or("HEAD")
+                .to_owned();
+
+            let result = match &self.parent {
+                Some(parent) => {
+                    repository.reference_matching(&branch, commit, true, parent.id(), message)
+                }
+                None => repository.reference(&branch, commit, false, message),
+            };
+
+            match result {
+                Err(err)
+                    if err.code() == ErrorCode::Modified || err.code() == ErrorCode::Exists =>
+                {
+                    Err(ErrorKind::Conflict)
+                }
+                other => other.map(|_| ()).map_err(Into::into),
+            }
+        })()
+        .map_err(Error::head)
+    }
+}
+
+/// An exclusive advisory lock on a lock file, released when dropped.
+///
+/// The lock is held by the open file, and is as such released by the kernel
+/// even if the writer holding it crashes, leaving no stale lock behind.
+pub(crate) struct Lock {
+    _file: File,
+}
+
+impl Lock {
+    /// Lock the file at `path`, waiting for it to be released if held by another writer.
+    pub(crate) fn acquire(path: &Path) -> Result<Self, ErrorKind> {
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+        let start = Instant::now();
+
+        while !file.try_lock_exclusive()? {
+            if start.elapsed() > LOCK_TIMEOUT {
+                return Err(ErrorKind::Locked);
+            }
+
+            std::thread::sleep(Duration::from_millis(25));
+        }
+
+        Ok(Self { _file: file })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        path::PathBuf,
+        time::{SystemTime, UNIX_EPOCH},
+    };
+
+    use crate::{
+        authority::Local,
+        entries::{Keychain, Repositories},
+        Id,
+    };
+
+    use super::*;
+
+    fn repository() -> (PathBuf, Repository) {
+        let storage = std::env::temp_dir().join(format!(
+            "furrow-transaction-{}-{}",
+            std::process::id(),
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .expect("The clock is before the epoch")
+                .as_nanos()
+        ));
+        let repository = Repository::init(&storage, &Id::global_authority())
+            .expect("Unable to init the repository");
+
+        (storage, repository)
+    }
+
+    fn key() -> ssh_key::PublicKey {
+        "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIL5wpJU3TRZj+OZpGu0wFYV/VzEAHtRvGOgVOK+40Gfq"
+            .parse()
+            .expect("Unable to parse the key")
+    }
+
+    #[test]
+    fn concurrent_bootstraps_commit_once() {
+        let (storage, _) = repository();
+
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    let repository = Repository::open(&storage, &Id::global_authority())
+                        .expect("Unable to open the repository");
+
+                    Local::load_or_init(&repository, &key()).expect("Unable to bootstrap");
+                });
+            }
+        });
+
+        let repository = Repository::open(&storage, &Id::global_authority())
+            .expect("Unable to open the repository");
+        let head = repository
+            .head()
+            .and_then(|head| head.peel_to_commit())
+            .expect("Unable to find the `HEAD` commit");
+
+        assert_eq!(head.parent_count(), 0);
+        assert!(Keychain::load(&repository).is_ok());
+        assert!(Repositories::load(&repository).is_ok());
+
+        fs::remove_dir_all(storage).expect("Unable to clean up the repository");
+    }
+
+    #[test]
+    fn locks_are_released_on_drop() {
+        let (storage, repository) = repository();
+
+        // A lock file left-over by a crashed writer holds no lock.
+        fs::write(repository.path().join(LOCK_NAME), "").expect("Unable to write the lock file");
+
+        let transaction = Transaction::begin(&repository).expect("Unable to begin");
+        let file = File::open(repository.path().join(LOCK_NAME)).expect("Unable to open the lock");
+        assert!(!file.try_lock_exclusive().expect("Unable to try the lock"));
+
+        drop(transaction);
+        Transaction::begin(&repository).expect("Unable to begin once released");
+
+        fs::remove_dir_all(storage).expect("Unable to clean up the repository");
+    }
+
+    #[test]
+    fn moved_head_conflicts() {
+        let (storage, repository) = repository();
+        Local::load_or_init(&repository, &key()).expect("Unable to bootstrap");
+
+        let mut transaction = Transaction::begin(&repository).expect("Unable to begin");
+        transaction
+            .stage(&Repositories::default())
+            .expect("Unable to stage");
+
+        // Move the `HEAD` behind the transaction's back, as a push would.
+        let head = repository
+            .head()
+            .and_then(|head| head.peel_to_commit())
+            .expect("Unable to find the `HEAD` commit");
+        let signature = crate::entries::signature().expect("Unable to create the signature");
+        repository
+            .commit(
+                Some("HEAD"),
+                &signature,
+                &signature,
+                "Concurrent",
+                &head.tree().expect("Unable to find the tree"),
+                &[&head],
+            )
+            .expect("Unable to commit");
+
+        let err = transaction
+            .commit("Conflicting", &signature)
+            .expect_err("The commit should conflict");
+        assert!(matches!(err.kind(), ErrorKind::Conflict));
+
+        fs::remove_dir_all(storage).expect("Unable to clean up the repository");
+    }
+}
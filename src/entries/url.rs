@@ -0,0 +1,69 @@
+use serde_with::{DeserializeFromStr, SerializeDisplay};
+
+/// An absolute URL, restricted to the `http` and `https` protocols.
+///
+/// Any other protocol, such as `file://` or `gopher://`, is refused,
+/// since it would give access to the server's own files or internal services.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, DeserializeFromStr, SerializeDisplay)]
+pub struct HttpUrl(String);
+
+impl HttpUrl {
+    /// The protocols allowed for the URLs, as named by `curl`'s `--proto`.
+    pub const PROTOCOLS: [&'static str; 2] = ["http", "https"];
+
+    /// Access the URL as a string slice.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::str::FromStr for HttpUrl {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.starts_with('-') || s.contains(|c: char| c.is_whitespace() || c.is_control()) {
+            return Err(format!("`{s}` is not a valid URL"));
+        }
+
+        let allowed = s.split_once("://").is_some_and(|(scheme, rest)| {
+            Self::PROTOCOLS.contains(&scheme.to_ascii_lowercase().as_str()) && !rest.is_empty()
+        });
+
+        if !allowed {
+            return Err(format!(
+                "`{s}` is not an allowed URL, only the {} protocols are supported",
+                Self::PROTOCOLS.join(", ")
+            ));
+        }
+
+        Ok(Self(s.into()))
+    }
+}
+
+impl std::fmt::Display for HttpUrl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+
+    #[rstest]
+    #[case("https://example.com/hook", true)]
+    #[case("http://10.0.0.1:8080/hook?token=x", true)]
+    #[case("HTTPS://example.com/hook", true)]
+    #[case("https://", false)]
+    #[case("file:///etc/passwd", false)]
+    #[case("gopher://localhost:6379/_FLUSHALL", false)]
+    #[case("dict://localhost:11211/stats", false)]
+    #[case("example.com/hook", false)]
+    #[case("-K/etc/passwd", false)]
+    #[case("https://example.com/a b", false)]
+    fn it_restricts_the_protocols(#[case] url: &str, #[case] valid: bool) {
+        assert_eq!(url.parse::<HttpUrl>().is_ok(), valid);
+    }
+}
@@ -0,0 +1,137 @@
+//! Versioning and migrations of the entries' schemas.
+
+use serde::Deserialize;
+use toml_edit::DocumentMut;
+
+use super::{Diagnostic, Entry, ErrorKind};
+
+/// A migration of an [`Entry`]'s document to the next version of it's schema.
+pub type Migration = fn(&mut DocumentMut);
+
+/// The key holding the version of the schema in the entries' documents.
+const KEY: &str = "version";
+
+/// The top of an [`Entry`]'s document, documents without a version being of the first one.
+#[derive(Deserialize)]
+struct Header {
+    #[serde(default = "first")]
+    version: u32,
+}
+
+fn first() -> u32 {
+    1
+}
+
+/// Parse the [`Entry`] from it's document, upgrading it in memory if it's from an older version.
+pub fn parse<A, T: Entry<A>>(content: &str) -> Result<T, ErrorKind> {
+    let migrated = migrate::<A, T>(content)?;
+    let content = migrated.as_ref().map_or(content, |(_, migrated)| migrated);
+
+    toml::from_str(content).map_err(|err| Diagnostic::new(T::PATH, content, &err).into())
+}
+
+/// Migrate the [`Entry`]'s document to the current version of it's schema, returning the version
+/// it was migrated from along with the migrated document, or [`None`] if it's already up-to-date.
+pub fn migrate<A, T: Entry<A>>(content: &str) -> Result<Option<(u32, String)>, ErrorKind> {
+    let version = toml::from_str::<Header>(content)
+        .map_err(|err| Diagnostic::new(T::PATH, content, &err))?
+        .version;
+
+    if version == 0 || version > T::VERSION {
+        return Err(ErrorKind::UnsupportedVersion {
+            version,
+            supported: T::VERSION,
+        });
+    }
+    if version == T::VERSION {
+        return Ok(None);
+    }
+
+    let mut document: DocumentMut = content
+        .parse()
+        .map_err(|err| Diagnostic::document(T::PATH, content, &err))?;
+    for migration in &T::MIGRATIONS[version as usize - 1..] {
+        migration(&mut document);
+    }
+    document.insert(KEY, toml_edit::value(i64::from(T::VERSION)));
+
+    Ok(Some((version, document.to_string())))
+}
+
+/// Serialize the [`Entry`] to a document, marked with the current version of it's schema.
+pub fn render<A, T: Entry<A>>(entry: &T) -> Result<String, toml::ser::Error> {
+    Ok(format!(
+        "{KEY} = {}\n\n{}",
+        T::VERSION,
+        toml::to_string_pretty(entry)?
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Serialize;
+
+    use super::*;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct Sample {
+        #[serde(default, skip_serializing, rename = "version")]
+        _version: serde::de::IgnoredAny,
+
+        name: String,
+    }
+
+    impl From<()> for Sample {
+        fn from(_value: ()) -> Self {
+            Self {
+                _version: Default::default(),
+                name: Default::default(),
+            }
+        }
+    }
+
+    impl Entry<()> for Sample {
+        const PATH: &'static str = "Sample.toml";
+        const MIGRATIONS: &'static [Migration] = &[|document| {
+            if let Some((key, title)) = document.remove_entry("title") {
+                let key = toml_edit::Key::new("name").with_leaf_decor(key.leaf_decor().clone());
+                document.insert_formatted(&key, title);
+            }
+        }];
+    }
+
+    #[test]
+    fn outdated_documents_are_migrated() {
+        let (version, migrated) = migrate::<(), Sample>("# Comment\ntitle = \"a\"\n")
+            .expect("Unable to migrate")
+            .expect("The document should be migrated");
+
+        assert_eq!(version, 1);
+        assert_eq!(migrated, "# Comment\nname = \"a\"\nversion = 2\n");
+        assert_eq!(
+            parse::<(), Sample>("title = \"a\"\n")
+                .expect("Unable to parse")
+                .name,
+            "a"
+        );
+    }
+
+    #[test]
+    fn current_documents_are_untouched() {
+        assert!(migrate::<(), Sample>("version = 2\nname = \"a\"\n")
+            .expect("Unable to migrate")
+            .is_none());
+    }
+
+    #[test]
+    fn newer_documents_are_rejected() {
+        assert!(matches!(
+            migrate::<(), Sample>("version = 3\nname = \"a\"\n"),
+            Err(ErrorKind::UnsupportedVersion {
+                version: 3,
+                supported: 2
+            })
+        ));
+    }
+}
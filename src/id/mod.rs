@@ -5,6 +5,8 @@ use std::{
     str::FromStr,
 };
 
+use serde_with::{DeserializeFromStr, SerializeDisplay};
+
 use super::AUTHORITY_REPOSITORY_NAME;
 
 mod error;
@@ -32,7 +34,7 @@ pub enum Kind {
 /// A repository [`Id`] is defined as a path without a leading `/`
 /// that does not contain any other component than [`path::Component::Normal`]
 /// that are parsed as a [`Base`] and a [`Name`].
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, DeserializeFromStr, SerializeDisplay)]
 pub struct Id {
     namespace: Option<Base>,
     repository: Name,
@@ -0,0 +1,185 @@
+//! The _Git LFS_ storage of the repositories, holding the objects content-addressed
+//! by their SHA-256 digest along with the file locks.
+
+use std::{
+    fs::File,
+    io::{self, Write},
+    path::PathBuf,
+};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use super::{entries, Repository};
+
+/// The directory holding the LFS storage, in each repository.
+const LFS_PATH: &str = "lfs";
+
+/// Whether the `oid` is a valid SHA-256 LFS object id.
+pub fn is_oid(oid: &str) -> bool {
+    oid.len() == 64 && oid.bytes().all(|c| matches!(c, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+/// A handle to the LFS objects of a repository.
+pub struct Objects {
+    root: PathBuf,
+}
+
+impl Objects {
+    /// Open the LFS objects of the `repository`.
+    pub fn new(repository: &Repository) -> Self {
+        Self {
+            root: repository.path().join(LFS_PATH),
+        }
+    }
+
+    /// Compute the path of the object with the `oid`, which must be valid.
+    pub fn path(&self, oid: &str) -> PathBuf {
+        self.root
+            .join("objects")
+            .join(&oid[0..2])
+            .join(&oid[2..4])
+            .join(oid)
+    }
+
+    /// Get the size of the object with the `oid`, if it exists.
+    pub fn size(&self, oid: &str) -> io::Result<Option<u64>> {
+        match std::fs::metadata(self.path(oid)) {
+            Ok(metadata) => Ok(Some(metadata.len())),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Start writing the object with the `oid`, which is only stored once verified with [`ObjectWriter::commit`].
+    pub fn writer(&self, oid: &str) -> io::Result<ObjectWriter> {
+        let directory = self.root.join("tmp");
+        std::fs::create_dir_all(&directory)?;
+
+        let temporary = directory.join(format!("{oid}-{:08x}", rand::random::<u32>()));
+
+        Ok(ObjectWriter {
+            file: File::create(&temporary)?,
+            temporary,
+            path: self.path(oid),
+            oid: oid.into(),
+            hasher: Sha256::new(),
+            size: 0,
+            committed: false,
+        })
+    }
+}
+
+/// A writer for an LFS object, verifying it's content against it's id.
+///
+/// The written data is discarded when the writer is dropped without being committed.
+pub struct ObjectWriter {
+    file: File,
+    temporary: PathBuf,
+    path: PathBuf,
+    oid: String,
+    hasher: Sha256,
+    size: u64,
+    committed: bool,
+}
+
+impl ObjectWriter {
+    /// Store the object if it's digest and `size` match, returning whether it did.
+    pub fn commit(mut self, size: u64) -> io::Result<bool> {
+        let digest = hex::encode(std::mem::take(&mut self.hasher).finalize());
+        if digest != self.oid || self.size != size {
+            return Ok(false);
+        }
+
+        self.file.sync_all()?;
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::rename(&self.temporary, &self.path)?;
+        self.committed = true;
+
+        Ok(true)
+    }
+}
+
+impl Drop for ObjectWriter {
+    fn drop(&mut self) {
+        if !self.committed {
+            let _ = std::fs::remove_file(&self.temporary);
+        }
+    }
+}
+
+impl Write for ObjectWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.file.write(buf)?;
+        self.hasher.update(&buf[..written]);
+        self.size += written as u64;
+
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// A lock on a file path of the repository, preventing others from modifying it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Lock {
+    /// The unique id of the lock.
+    pub id: String,
+
+    /// The path of the locked file.
+    pub path: String,
+
+    /// The RFC 3339 timestamp of the lock creation.
+    pub locked_at: String,
+
+    /// The fingerprint of the key owning the lock.
+    pub owner: String,
+
+    /// The displayed name of the owner of the lock.
+    pub ownername: String,
+}
+
+/// The file locks of a repository, ordered by id.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Locks(pub Vec<Lock>);
+
+impl Locks {
+    /// Load the locks of the `repository`.
+    pub fn load(repository: &Repository) -> io::Result<Self> {
+        match std::fs::read(Self::path(repository)) {
+            Ok(content) => Ok(serde_json::from_slice(&content)?),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Apply the modification `f` to the locks of the `repository`,
+    /// holding the locks file for the duration of the modification.
+    pub fn update<T>(repository: &Repository, f: impl FnOnce(&mut Self) -> T) -> io::Result<T> {
+        let path = Self::path(repository);
+        std::fs::create_dir_all(repository.path().join(LFS_PATH))?;
+
+        let _lock =
+            entries::Lock::acquire(&path.with_extension("json.lock")).map_err(|err| match err {
+                entries::ErrorKind::Io(err) => err,
+                err => io::Error::other(err),
+            })?;
+
+        let mut locks = Self::load(repository)?;
+        let output = f(&mut locks);
+
+        let temporary = path.with_extension("json.tmp");
+        std::fs::write(&temporary, serde_json::to_vec_pretty(&locks)?)?;
+        std::fs::rename(temporary, &path)?;
+
+        Ok(output)
+    }
+
+    fn path(repository: &Repository) -> PathBuf {
+        repository.path().join(LFS_PATH).join("locks.json")
+    }
+}
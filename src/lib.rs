@@ -36,3 +36,7 @@ pub use id::Id;
 
 pub mod authority;
 pub mod entries;
+pub mod lfs;
+pub mod proposal;
+pub mod queue;
+pub mod status;
@@ -0,0 +1,243 @@
+//! Change _proposals_, submitted by pushing to `refs/for/<branch>`,
+//! stored as `refs/proposals/<number>/head` references along with their metadata.
+
+use std::{io, path::PathBuf};
+
+use parse_display::Display;
+use serde::{Deserialize, Serialize};
+
+use super::Repository;
+
+/// The prefix of the references pushed to by the clients to submit a proposal.
+pub const FOR_PREFIX: &str = "refs/for/";
+
+/// The prefix of the references holding the proposals, in each repository.
+pub const PROPOSALS_PREFIX: &str = "refs/proposals/";
+
+/// The directory holding the proposals metadata, in each repository.
+const PROPOSALS_PATH: &str = "proposals";
+
+/// A change proposed for inclusion in a branch of a repository.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Proposal {
+    /// The number of the proposal, unique in the repository.
+    #[serde(skip)]
+    pub number: u64,
+
+    /// The name of the branch the changes are proposed to.
+    pub target: String,
+
+    /// The topic of the proposal, as set by the `topic` push option.
+    pub topic: Option<String>,
+
+    /// The fingerprint of the key which submitted the proposal.
+    pub author: String,
+
+    /// The UNIX timestamp, in seconds, of the submission of the proposal.
+    pub created: i64,
+
+    /// The UNIX timestamp, in seconds, of the last update of the proposal.
+    pub updated: i64,
+
+    /// The current state of the proposal.
+    pub state: State,
+
+    /// The metadata of the patch series the proposal has been submitted as, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub series: Option<Series>,
+}
+
+/// The metadata of a patch series, as produced by `git format-patch`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Series {
+    /// The subject of the cover letter, or of the first patch, without it's `[PATCH]` prefix.
+    pub subject: String,
+
+    /// The sender of the series, from the `From` header of it's first message.
+    pub from: String,
+
+    /// The count of patches in the series.
+    pub patches: usize,
+}
+
+/// The state of a [`Proposal`].
+#[derive(Debug, Display, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[display(style = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum State {
+    /// The proposal awaits review.
+    Open,
+
+    /// The proposal has been merged in it's target branch.
+    Merged,
+
+    /// The proposal has been closed without being merged.
+    Closed,
+}
+
+impl Proposal {
+    /// Compute the name of the reference holding the proposal's changes.
+    pub fn refname(&self) -> String {
+        format!("{PROPOSALS_PREFIX}{}/head", self.number)
+    }
+
+    /// List all the proposals of the `repository`, ordered by number.
+    pub fn list(repository: &Repository) -> io::Result<Vec<Self>> {
+        let entries = match std::fs::read_dir(Self::directory(repository)) {
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            other => other?,
+        };
+
+        let mut proposals = Vec::new();
+        for entry in entries {
+            let name = entry?.file_name();
+            let Some(number) = name
+                .to_str()
+                .and_then(|name| name.strip_suffix(".json"))
+                .and_then(|number| number.parse().ok())
+            else {
+                continue;
+            };
+
+            if let Some(proposal) = Self::load(repository, number)? {
+                proposals.push(proposal);
+            }
+        }
+        proposals.sort_by_key(|proposal| proposal.number);
+
+        Ok(proposals)
+    }
+
+    /// Load the proposal with the `number` from the `repository`, if it exists.
+    pub fn load(repository: &Repository, number: u64) -> io::Result<Option<Self>> {
+        match std::fs::read(Self::directory(repository).join(format!("{number}.json"))) {
+            // The number has been reserved, but the proposal is not yet saved.
+            Ok(content) if content.is_empty() => Ok(None),
+            Ok(content) => Ok(Some(Self {
+                number,
+                ..serde_json::from_slice(&content)?
+            })),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Store the proposal in the `repository` under a new number, which is assigned to it.
+    pub fn create(&mut self, repository: &Repository) -> io::Result<()> {
+        self.reserve(repository)?;
+        self.save(repository)
+    }
+
+    /// Reserve a new number for the proposal in the `repository`, which is assigned to it,
+    /// the proposal being left out of the listings until it's saved or [`Proposal::release`]d.
+    pub fn reserve(&mut self, repository: &Repository) -> io::Result<()> {
+        let directory = Self::directory(repository);
+        std::fs::create_dir_all(&directory)?;
+
+        self.number = Self::list(repository)?
+            .last()
+            .map_or(1, |proposal| proposal.number + 1);
+
+        // Reserve the number, in case of concurrent submissions.
+        loop {
+            match std::fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(directory.join(format!("{}.json", self.number)))
+            {
+                Err(err) if err.kind() == io::ErrorKind::AlreadyExists => self.number += 1,
+                other => break other.map(drop),
+            }
+        }
+    }
+
+    /// Release the number reserved for the proposal in the `repository`, before it's saved.
+    pub fn release(&self, repository: &Repository) -> io::Result<()> {
+        std::fs::remove_file(Self::directory(repository).join(format!("{}.json", self.number)))
+    }
+
+    /// Atomically save the proposal's metadata in the `repository`.
+    pub fn save(&self, repository: &Repository) -> io::Result<()> {
+        let directory = Self::directory(repository);
+        let temporary = directory.join(format!(".{}.json", self.number));
+
+        std::fs::write(&temporary, serde_json::to_vec_pretty(self)?)?;
+        std::fs::rename(temporary, directory.join(format!("{}.json", self.number)))
+    }
+
+    fn directory(repository: &Repository) -> PathBuf {
+        repository.path().join(PROPOSALS_PATH)
+    }
+}
+
+impl Series {
+    /// Parse the metadata of the series from it's `mbox` representation,
+    /// returning [`None`] if it contains no patch.
+    pub fn parse(mbox: &str) -> Option<Self> {
+        let mut subject = None;
+        let mut from = None;
+        let mut patches = 0;
+
+        for message in mbox
+            .split("\nFrom ")
+            .filter(|message| !message.trim().is_empty())
+        {
+            let (headers, body) = message.split_once("\n\n").unwrap_or((message, ""));
+            let header = |name: &str| {
+                headers.lines().find_map(|line| {
+                    line.strip_prefix(name)
+                        .and_then(|line| line.strip_prefix(':'))
+                        .map(str::trim)
+                })
+            };
+
+            from = from.or_else(|| header("From").map(String::from));
+            subject = subject.or_else(|| {
+                header("Subject").map(|subject| {
+                    match subject.strip_prefix('[').and_then(|s| s.split_once(']')) {
+                        Some((_, subject)) => subject.trim().into(),
+                        None => subject.into(),
+                    }
+                })
+            });
+
+            if body.contains("\ndiff --git ") {
+                patches += 1;
+            }
+        }
+
+        (patches > 0).then(|| Self {
+            subject: subject.unwrap_or_default(),
+            from: from.unwrap_or_default(),
+            patches,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+
+    const COVER: &str = "From 0000 Mon Sep 17 00:00:00 2001\nFrom: A <a@example.com>\nSubject: [PATCH 0/2] Add a feature\n\nThe cover letter.\n";
+    const FIRST: &str = "From 1111 Mon Sep 17 00:00:00 2001\nFrom: A <a@example.com>\nSubject: [PATCH 1/2] Add a file\n\n---\ndiff --git a/f b/f\n";
+    const SECOND: &str = "From 2222 Mon Sep 17 00:00:00 2001\nFrom: A <a@example.com>\nSubject: [PATCH 2/2] Edit a file\n\n---\ndiff --git a/f b/f\n";
+
+    #[rstest]
+    #[case(&[COVER, FIRST, SECOND], Some(("Add a feature", 2)))]
+    #[case(&[FIRST, SECOND], Some(("Add a file", 2)))]
+    #[case(&[SECOND], Some(("Edit a file", 1)))]
+    #[case(&[COVER], None)]
+    #[case(&[], None)]
+    fn it_parses_series(#[case] messages: &[&str], #[case] expected: Option<(&str, usize)>) {
+        assert_eq!(
+            Series::parse(&messages.concat()),
+            expected.map(|(subject, patches)| Series {
+                subject: subject.into(),
+                from: "A <a@example.com>".into(),
+                patches,
+            })
+        );
+    }
+}
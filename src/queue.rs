@@ -0,0 +1,134 @@
+//! A persistent, file-backed queue of tasks, stored in the `storage` path.
+//!
+//! Tasks are pushed by short-lived processes such as the hooks, and processed
+//! in the background by the server, surviving restarts in between.
+
+use std::{
+    io,
+    marker::PhantomData,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+/// The directory containing the queues, relative to the `storage` path.
+pub const QUEUES_PATH: &str = ".queue";
+
+/// A named [`Queue`] of tasks of type `T`.
+#[derive(Debug)]
+pub struct Queue<T> {
+    path: PathBuf,
+    _marker: PhantomData<T>,
+}
+
+/// A task pulled from a [`Queue`], with it's delivery metadata.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Task<T> {
+    #[serde(skip)]
+    name: String,
+
+    /// The number of previous failed attempts at processing the task.
+    pub attempts: u32,
+
+    /// The UNIX timestamp, in seconds, before which the task may not be processed.
+    pub not_before: u64,
+
+    /// The task's actual content.
+    pub item: T,
+}
+
+impl<T: Serialize + DeserializeOwned> Queue<T> {
+    /// Open the queue named `name` in the `storage` path.
+    pub fn new(storage: &Path, name: &str) -> Self {
+        Self {
+            path: storage.join(QUEUES_PATH).join(name),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Push a new `item` to the queue, to be processed as soon as possible.
+    pub fn push(&self, item: T) -> io::Result<()> {
+        let name = format!(
+            "{:020}-{:08x}.json",
+            now().as_nanos(),
+            rand::random::<u32>()
+        );
+
+        self.write(&Task {
+            name,
+            attempts: 0,
+            not_before: 0,
+            item,
+        })
+    }
+
+    /// List the tasks ready to be processed, in the order they were pushed.
+    pub fn ready(&self) -> io::Result<Vec<Task<T>>> {
+        let entries = match std::fs::read_dir(&self.path) {
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            other => other?,
+        };
+
+        let mut names = entries
+            .map(|entry| Ok(entry?.file_name().to_string_lossy().into_owned()))
+            .filter(|name| {
+                name.as_ref().map_or(true, |name| {
+                    name.ends_with(".json") && !name.starts_with('.')
+                })
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+        names.sort();
+
+        let now = now().as_secs();
+        let mut tasks = Vec::with_capacity(names.len());
+        for name in names {
+            let content = std::fs::read(self.path.join(&name))?;
+            let task = match serde_json::from_slice::<Task<T>>(&content) {
+                Ok(task) => Task { name, ..task },
+                Err(err) => {
+                    tracing::error!("Discarding malformed task `{name}`: {err}");
+
+                    std::fs::remove_file(self.path.join(&name))?;
+                    continue;
+                }
+            };
+
+            if task.not_before <= now {
+                tasks.push(task);
+            }
+        }
+
+        Ok(tasks)
+    }
+
+    /// Remove the `task` from the queue, after it has been processed.
+    pub fn complete(&self, task: Task<T>) -> io::Result<()> {
+        std::fs::remove_file(self.path.join(&task.name))
+    }
+
+    /// Reschedule the `task` after a failed attempt, to be processed after the `delay`.
+    pub fn retry(&self, task: Task<T>, delay: Duration) -> io::Result<()> {
+        self.write(&Task {
+            attempts: task.attempts + 1,
+            not_before: (now() + delay).as_secs(),
+            ..task
+        })
+    }
+
+    /// Atomically write the `task` to the queue.
+    fn write(&self, task: &Task<T>) -> io::Result<()> {
+        std::fs::create_dir_all(&self.path)?;
+
+        // The temporary file is hidden and suffixed so that it's never listed as a ready task.
+        let temporary = self.path.join(format!(".{}.tmp", task.name));
+        std::fs::write(&temporary, serde_json::to_vec(task)?)?;
+        std::fs::rename(temporary, self.path.join(&task.name))
+    }
+}
+
+fn now() -> Duration {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+}
@@ -0,0 +1,122 @@
+//! Commit _statuses_, reported by external services or the server's jobs,
+//! stored as notes on the commits under the [`NOTES_REF`] reference.
+
+use std::collections::BTreeMap;
+
+use git2::Oid;
+use parse_display::{Display, FromStr};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::Repository;
+
+/// The notes reference holding the statuses, in each repository.
+pub const NOTES_REF: &str = "refs/notes/furrow-status";
+
+/// The maximum count of attempts at writing the notes, in case of concurrent writes.
+const MAX_ATTEMPTS: usize = 3;
+
+/// The statuses of a single commit, by context, such as `ci/test`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Statuses(pub BTreeMap<String, Status>);
+
+/// The status of a commit, in a single context.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Status {
+    /// The state of the commit in the context.
+    pub state: State,
+
+    /// A short description of the state.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+
+    /// The UNIX timestamp, in seconds, of the last update of the status.
+    pub updated: i64,
+
+    /// The key fingerprint, or the server component, which reported the status.
+    pub reporter: String,
+}
+
+/// The state of a commit in a context.
+#[derive(Debug, Display, FromStr, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[display(style = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum State {
+    /// The check is in progress.
+    Pending,
+
+    /// The check succeeded.
+    Success,
+
+    /// The check failed.
+    Failure,
+
+    /// The check couldn't be carried out.
+    Error,
+}
+
+/// An [`enum@Error`] that can occur while manipulating [`Statuses`].
+#[derive(Debug, Error)]
+pub enum Error {
+    /// A _git repository_ error.
+    #[error("Git error: {0}")]
+    Git(#[from] git2::Error),
+
+    /// A _statuses serialization_ error.
+    #[error(transparent)]
+    Ser(#[from] toml::ser::Error),
+
+    /// A _statuses deserialization_ error.
+    #[error(transparent)]
+    De(#[from] toml::de::Error),
+}
+
+impl Statuses {
+    /// Load the statuses of the `commit` in the `repository`, empty if none were reported.
+    pub fn load(repository: &Repository, commit: Oid) -> Result<Self, Error> {
+        match repository.find_note(Some(NOTES_REF), commit) {
+            Ok(note) => Ok(toml::from_str(note.message().unwrap_or_default())?),
+            Err(err) if err.code() == git2::ErrorCode::NotFound => Ok(Self::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Set the `status` of the `commit` in the `repository` for the `context`,
+    /// replacing any previous status in this context.
+    pub fn set(
+        repository: &Repository,
+        commit: Oid,
+        context: &str,
+        status: Status,
+    ) -> Result<(), Error> {
+        let signature = git2::Signature::now("furrow", "git@server.commit")?;
+        let mut attempt = 0;
+
+        loop {
+            let mut statuses = Self::load(repository, commit)?;
+            statuses.0.insert(context.into(), status.clone());
+
+            match repository.note(
+                &signature,
+                &signature,
+                Some(NOTES_REF),
+                commit,
+                &toml::to_string_pretty(&statuses)?,
+                true,
+            ) {
+                // Another status has been written concurrently, retry with the new statuses.
+                Err(err) if err.code() == git2::ErrorCode::Modified && attempt < MAX_ATTEMPTS => {
+                    attempt += 1
+                }
+                other => return other.map(|_| ()).map_err(Into::into),
+            }
+        }
+    }
+
+    /// Whether the commit has a successful status for the `context`.
+    pub fn is_success(&self, context: &str) -> bool {
+        self.0
+            .get(context)
+            .is_some_and(|status| status.state == State::Success)
+    }
+}